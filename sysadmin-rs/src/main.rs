@@ -3,21 +3,39 @@ use rmp_serde::Serializer;
 use serde::{self, Serialize};
 use std::io::{self, Write};
 use sysadminlib::packages::match_packages_command;
-use sysadminlib::{get_os, CliCommands, OutputType};
+use sysadminlib::packages::progress::set_progress_sink;
+use sysadminlib::system::match_system_command;
+use sysadminlib::{CliCommands, Exit, OutputType, StreamRecord};
 
 fn main() -> Result<(), i32> {
     let commands = sysadminlib::CliArgs::parse();
-    let os_id = get_os();
+    let output = commands.output;
+
+    if commands.stream {
+        set_progress_sink(move |event| print_record(output, &StreamRecord::Progress(event)));
+    }
 
     let exit = match &commands.command {
-        CliCommands::Packages(packagescli) => {
-            match_packages_command(&os_id, packagescli, commands.output)
-        }
+        CliCommands::Packages(packagescli) => match_packages_command(packagescli, output),
+        CliCommands::System(systemcli) => match_system_command(systemcli, output),
     };
 
-    match &commands.output {
+    if commands.stream {
+        print_record(output, &StreamRecord::Exit(&exit));
+    } else {
+        print_exit(output, &exit);
+    }
+
+    return match exit.exit_code {
+        0 => Ok(()),
+        _ => Err(exit.exit_code),
+    };
+}
+
+fn print_exit(output: OutputType, exit: &Exit) {
+    match output {
         OutputType::Stdout => println!("{}", exit.out),
-        OutputType::Json => println!("{}", serde_json::to_string_pretty(&exit).unwrap()),
+        OutputType::Json => println!("{}", serde_json::to_string_pretty(exit).unwrap()),
         OutputType::Msgpack => {
             let mut buf = Vec::new();
             let mut s = Serializer::new(&mut buf);
@@ -25,9 +43,28 @@ fn main() -> Result<(), i32> {
             io::stdout().write_all(&buf).unwrap_or_default();
         }
     }
+}
 
-    return match exit.exit_code {
-        0 => Ok(()),
-        _ => Err(exit.exit_code),
-    };
+/// Prints a record of the "--stream" output. "stdout" prints the lines of the
+/// package manager as they come, and the output of the Exit only if it failed
+fn print_record(output: OutputType, record: &StreamRecord) {
+    let mut stdout = io::stdout().lock();
+    match output {
+        OutputType::Stdout => match record {
+            StreamRecord::Progress(event) => writeln!(stdout, "{}", event.line),
+            StreamRecord::Exit(exit) if exit.exit_code != 0 => writeln!(stdout, "{}", exit.out),
+            StreamRecord::Exit(_) => Ok(()),
+        },
+        OutputType::Json => writeln!(stdout, "{}", serde_json::to_string(record).unwrap()),
+        OutputType::Msgpack => {
+            let mut buf = Vec::new();
+            let mut s = Serializer::new(&mut buf);
+            record.serialize(&mut s).unwrap();
+            stdout
+                .write_all(&(buf.len() as u32).to_be_bytes())
+                .and_then(|_| stdout.write_all(&buf))
+        }
+    }
+    .and_then(|_| stdout.flush())
+    .unwrap_or_default();
 }
@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+
+/// Errors from sysadmin commands
+#[derive(Debug)]
+pub enum SysadminError {
+    /// The native program for an operation is not installed
+    BackendMissing { command: String },
+    /// No backend exists for the operating system
+    UnsupportedOs { os_id: String },
+    /// The operating system could not be determined
+    UnknownOs { message: String },
+    /// The native program exited with a non-zero exit code
+    CommandFailed {
+        command: String,
+        exit_code: i32,
+        stderr: String,
+    },
+    /// The output of the native program could not be parsed
+    Parse { line: String, message: String },
+    /// The operation needs more privileges than the program has
+    PermissionDenied { command: String, message: String },
+    /// The native program succeeded, but its output wasn't valid UTF-8
+    UnreadableOutput { command: String },
+    /// Any other error from running the native program
+    Io { command: String, message: String },
+    /// The operation can't be done with the given arguments or backend
+    UnsupportedOperation { message: String },
+    /// Something the operation is about, like a repository, doesn't exist
+    NotFound { message: String },
+}
+
+/// Machine readable kind of a SysadminError, sent in an Exit
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    BackendMissing,
+    UnsupportedOs,
+    UnknownOs,
+    CommandFailed,
+    ParseFailure,
+    PermissionDenied,
+    UnreadableOutput,
+    Io,
+    UnsupportedOperation,
+    NotFound,
+}
+
+impl SysadminError {
+    pub fn kind(&self) -> ErrorKind {
+        return match self {
+            SysadminError::BackendMissing { .. } => ErrorKind::BackendMissing,
+            SysadminError::UnsupportedOs { .. } => ErrorKind::UnsupportedOs,
+            SysadminError::UnknownOs { .. } => ErrorKind::UnknownOs,
+            SysadminError::CommandFailed { .. } => ErrorKind::CommandFailed,
+            SysadminError::Parse { .. } => ErrorKind::ParseFailure,
+            SysadminError::PermissionDenied { .. } => ErrorKind::PermissionDenied,
+            SysadminError::UnreadableOutput { .. } => ErrorKind::UnreadableOutput,
+            SysadminError::Io { .. } => ErrorKind::Io,
+            SysadminError::UnsupportedOperation { .. } => ErrorKind::UnsupportedOperation,
+            SysadminError::NotFound { .. } => ErrorKind::NotFound,
+        };
+    }
+
+    /// Exit code to exit the program with for this error
+    pub fn exit_code(&self) -> i32 {
+        return match self {
+            SysadminError::CommandFailed { exit_code, .. } => *exit_code,
+            SysadminError::BackendMissing { .. } => 127,
+            SysadminError::PermissionDenied { .. } => 126,
+            SysadminError::UnsupportedOperation { .. } => 2,
+            _ => 1,
+        };
+    }
+
+    /// Creates a parse error for a line of output that couldn't be understood
+    pub fn parse(line: &str, message: &str) -> SysadminError {
+        return SysadminError::Parse {
+            line: line.to_string(),
+            message: message.to_string(),
+        };
+    }
+
+    /// Creates an error for a program that could not be started
+    pub fn spawn(command: &str, error: io::Error) -> SysadminError {
+        return match error.kind() {
+            io::ErrorKind::NotFound => SysadminError::BackendMissing {
+                command: command.to_string(),
+            },
+            io::ErrorKind::PermissionDenied => SysadminError::PermissionDenied {
+                command: command.to_string(),
+                message: error.to_string(),
+            },
+            _ => SysadminError::Io {
+                command: command.to_string(),
+                message: error.to_string(),
+            },
+        };
+    }
+
+    /// Creates an error for a program that exited with a non-zero exit code.
+    /// Package managers report missing privileges in different ways, so stderr is checked for them
+    pub fn failed(command: &str, exit_code: i32, stderr: String) -> SysadminError {
+        let lowercase = stderr.to_lowercase();
+        let denied = [
+            "permission denied",
+            "superuser privileges",
+            "root privileges",
+            "are you root",
+            "unless you are root",
+            "must be root",
+        ];
+        if denied.iter().any(|message| lowercase.contains(message)) {
+            return SysadminError::PermissionDenied {
+                command: command.to_string(),
+                message: stderr,
+            };
+        }
+        return SysadminError::CommandFailed {
+            command: command.to_string(),
+            exit_code,
+            stderr,
+        };
+    }
+}
+
+impl fmt::Display for SysadminError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            SysadminError::BackendMissing { command } => {
+                write!(f, "{} is not installed or could not be found", command)
+            }
+            SysadminError::UnsupportedOs { os_id } => {
+                write!(f, "This operating system ({}) is not supported", os_id)
+            }
+            SysadminError::UnknownOs { message } => {
+                write!(f, "Could not determine the operating system: {}", message)
+            }
+            SysadminError::CommandFailed { stderr, .. } => write!(f, "{}", stderr),
+            SysadminError::Parse { line, message } => {
+                write!(f, "Could not parse output ({}): \"{}\"", message, line)
+            }
+            SysadminError::PermissionDenied { command, message } => {
+                write!(f, "{} needs more privileges: {}", command, message)
+            }
+            SysadminError::UnreadableOutput { .. } => write!(
+                f,
+                "Operation was successful, but the output could not be read from the native package manager"
+            ),
+            SysadminError::Io { command, message } => {
+                write!(f, "Could not run {}: {}", command, message)
+            }
+            SysadminError::UnsupportedOperation { message } => write!(f, "{}", message),
+            SysadminError::NotFound { message } => write!(f, "{}", message),
+        };
+    }
+}
+
+impl std::error::Error for SysadminError {}
@@ -1,9 +1,14 @@
 use clap::{Parser, Subcommand, ValueEnum};
+use error::{ErrorKind, SysadminError};
+use packages::progress::ProgressEvent;
 use packages::PackagesResponse;
 use serde::{Deserialize, Serialize};
-use std::fs;
+use system::OsInfo;
 
+pub mod error;
+pub mod os;
 pub mod packages;
+pub mod system;
 
 //========================================
 // Commands and arguments for the program
@@ -17,11 +22,17 @@ pub struct CliArgs {
 
     #[arg(value_enum, short, long, default_value = "stdout")]
     pub output: OutputType,
+
+    /// Print progress events while the command runs and the Exit at the end,
+    /// as lines of JSON or as msgpack messages prefixed with their length
+    #[arg(long)]
+    pub stream: bool,
 }
 
 #[derive(Subcommand)]
 pub enum CliCommands {
     Packages(PackagesArgs),
+    System(SystemArgs),
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -39,6 +50,38 @@ pub enum OutputType {
 pub struct PackagesArgs {
     #[command(subcommand)]
     pub command: PackagesCommands,
+
+    /// Use this package manager instead of detecting it
+    #[arg(value_enum, short, long, global = true)]
+    pub backend: Option<Backend>,
+
+    /// Manage the packages of the native package manager or of an app store
+    #[arg(value_enum, short, long, global = true, default_value = "native")]
+    pub source: Source,
+
+    /// Only use the per-user installation of the source (flatpak)
+    #[arg(short, long, global = true)]
+    pub user: bool,
+
+    /// Combine the native package manager, flatpak and snap, whichever are installed (list, search and update)
+    #[arg(long, global = true, conflicts_with = "source")]
+    pub all_sources: bool,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Source {
+    Native,
+    Flatpak,
+    Snap,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Backend {
+    Apk,
+    Apt,
+    Dnf,
+    Pacman,
+    Zypper,
 }
 
 #[derive(Subcommand)]
@@ -65,15 +108,166 @@ pub enum PackagesCommands {
     Update {
         #[arg(short, long)]
         fake: bool,
+
+        /// Only show what would be updated, without changing anything
+        #[arg(long, conflicts_with = "fake")]
+        dry_run: bool,
+
+        /// Only apply the pending security advisories
+        #[arg(long, conflicts_with_all = ["fake", "advisory"])]
+        security: bool,
+
+        /// Only apply the pending advisory with this id
+        #[arg(long, conflicts_with = "fake")]
+        advisory: Option<String>,
     },
     Install {
         packages: Option<Vec<String>>,
+
+        /// Only show what would be installed, without changing anything
+        #[arg(long)]
+        dry_run: bool,
     },
     Uninstall {
         packages: Option<Vec<String>>,
+
+        /// Only show what would be uninstalled, without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Show the past transactions of the package manager, newest first
+    History {
+        #[command(subcommand)]
+        command: Option<HistoryCommands>,
+
+        /// Number of transactions to show
+        #[arg(short, long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Show or change the repositories packages come from
+    Repos {
+        #[command(subcommand)]
+        command: Option<ReposCommands>,
+    },
+    /// Hold packages at their installed version, updates leave them alone
+    Hold {
+        #[arg(required = true)]
+        packages: Vec<String>,
+    },
+    /// Release held packages, so they are updated again
+    Unhold {
+        #[arg(required = true)]
+        packages: Vec<String>,
+    },
+    /// Show the held packages and the versions they are held at
+    Holds,
+    /// Show the pending advisories and the updates that apply them
+    Advisories {
+        /// Only show security advisories
+        #[arg(long)]
+        security: bool,
+    },
+    /// Uninstall the dependencies that no installed package needs anymore
+    Autoremove {
+        /// Only show what would be uninstalled, without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Remove the downloaded packages and metadata from the cache of the package manager
+    Clean,
+    /// Show the packages a package depends on
+    Depends {
+        package: String,
+    },
+    /// Show the installed packages that depend on a package
+    Rdepends {
+        package: String,
+    },
+    /// Show the chains of installed packages that need a package, up to the ones installed explicitly
+    Why {
+        package: String,
+    },
+    /// Show the installed package that owns a file
+    Owner {
+        path: String,
+    },
+    /// Show the files an installed package installs
+    Files {
+        package: String,
+    },
+    /// Check the installed files of packages against the package database and show the ones
+    /// that were changed or are missing, checks every package if none are given
+    Verify {
+        packages: Option<Vec<String>>,
     },
 }
 
+#[derive(Subcommand)]
+pub enum HistoryCommands {
+    /// Revert a transaction of the history (dnf)
+    Undo { id: String },
+}
+
+#[derive(Subcommand)]
+pub enum ReposCommands {
+    /// Show the configured repositories
+    List,
+    /// Enable a repository by its id
+    Enable { id: String },
+    /// Disable a repository by its id
+    Disable { id: String },
+    /// Add a repository with one or more URLs
+    Add {
+        #[arg(required = true)]
+        urls: Vec<String>,
+
+        /// Id of the repository, made from the first URL if not given. It tags the repository on apk
+        #[arg(long)]
+        id: Option<String>,
+
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Add the repository disabled
+        #[arg(long)]
+        disabled: bool,
+
+        /// Don't check the signatures of its packages
+        #[arg(long)]
+        no_gpgcheck: bool,
+
+        /// Priority of the repository (dnf, zypper)
+        #[arg(long)]
+        priority: Option<i32>,
+
+        /// Suites of the repository, e.g. "bookworm" (apt)
+        #[arg(long)]
+        suites: Vec<String>,
+
+        /// Components of the repository, e.g. "main" (apt)
+        #[arg(long)]
+        components: Vec<String>,
+    },
+    /// Remove a repository by its id
+    Remove { id: String },
+}
+
+// Arguments and commands for "system" subcommand
+//================================================
+
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct SystemArgs {
+    #[command(subcommand)]
+    pub command: SystemCommands,
+}
+
+#[derive(Subcommand)]
+pub enum SystemCommands {
+    /// Show the OS, kernel, hostname, uptime, CPU and memory of this host
+    Info,
+}
+
 //========================================
 // Helper structs and methods
 //========================================
@@ -86,18 +280,29 @@ pub struct Exit {
     pub out: String,
     packages_response: Option<PackagesResponse>,
     error_message: Option<String>,
+    error_kind: Option<ErrorKind>,
+    os_info: Option<OsInfo>,
 }
 
-/// Gets the OS from "/etc/os-release" on Linux distributions
-pub fn get_os() -> String {
-    let contents =
-        fs::read_to_string("/etc/os-release").expect("Could not determine the operating system");
-    let lines = contents.lines();
-    for line in lines {
-        let value: Vec<&str> = line.split("=").collect();
-        if value[0] == "ID" {
-            return value[1].to_string();
-        }
+impl Exit {
+    /// Creates the Exit for a failed sysadmin command
+    pub fn from_error(real_command: &'static str, out: &str, error: SysadminError) -> Exit {
+        return Exit {
+            exit_code: error.exit_code(),
+            real_command,
+            out: out.to_string(),
+            packages_response: None,
+            error_message: Some(error.to_string()),
+            error_kind: Some(error.kind()),
+            os_info: None,
+        };
     }
-    return "none".to_string();
+}
+
+/// A record of the "--stream" output, the progress events are followed by a single Exit
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamRecord<'a> {
+    Progress(&'a ProgressEvent),
+    Exit(&'a Exit),
 }
@@ -0,0 +1,104 @@
+use crate::error::SysadminError;
+use std::{env, fs};
+
+/// Environment variable with the path of a file to read instead of "/etc/os-release"
+pub const OS_RELEASE_VAR: &str = "SYSADMIN_OS_RELEASE";
+
+/// Identification of the operating system from its os-release file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OsRelease {
+    /// e.g. "rocky"
+    pub id: String,
+    /// IDs of the distributions this one is derived from, closest first, e.g. ["rhel", "centos", "fedora"]
+    pub id_like: Vec<String>,
+    /// e.g. "9.3"
+    pub version_id: Option<String>,
+    /// e.g. "Rocky Linux"
+    pub name: Option<String>,
+    /// e.g. "Rocky Linux 9.3 (Blue Onyx)"
+    pub pretty_name: Option<String>,
+}
+
+impl OsRelease {
+    /// Reads "/etc/os-release", falling back to "/usr/lib/os-release".
+    /// The file in SYSADMIN_OS_RELEASE is read instead if it is set
+    pub fn read() -> Result<OsRelease, SysadminError> {
+        let paths = match env::var(OS_RELEASE_VAR) {
+            Ok(path) => vec![path],
+            Err(_) => vec![
+                "/etc/os-release".to_string(),
+                "/usr/lib/os-release".to_string(),
+            ],
+        };
+        let mut message = String::new();
+        for path in paths {
+            match fs::read_to_string(&path) {
+                Ok(contents) => return Ok(OsRelease::parse(&contents)),
+                Err(error) => message = format!("{}: {}", path, error),
+            }
+        }
+        return Err(SysadminError::UnknownOs { message });
+    }
+
+    /// Parses the "KEY=value" lines of an os-release file, e.g. 'ID_LIKE="rhel centos fedora"'
+    pub fn parse(contents: &str) -> OsRelease {
+        let mut os = OsRelease {
+            id: "linux".to_string(),
+            id_like: vec![],
+            version_id: None,
+            name: None,
+            pretty_name: None,
+        };
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = unquote(value);
+            match key {
+                "ID" => os.id = value.to_lowercase(),
+                "ID_LIKE" => {
+                    os.id_like = value
+                        .split_whitespace()
+                        .map(|id| id.to_lowercase())
+                        .collect()
+                }
+                "VERSION_ID" => os.version_id = Some(value),
+                "NAME" => os.name = Some(value),
+                "PRETTY_NAME" => os.pretty_name = Some(value),
+                _ => {}
+            }
+        }
+        return os;
+    }
+
+    /// The ID followed by the ID_LIKE IDs, in the order to look for a backend
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        return std::iter::once(self.id.as_str()).chain(self.id_like.iter().map(|id| id.as_str()));
+    }
+}
+
+/// Removes the shell quoting of an os-release value, e.g. "\"Fedora Linux 40\"" or "'arch'"
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        return value[1..value.len() - 1].to_string();
+    }
+    let value = match value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        true => &value[1..value.len() - 1],
+        false => value,
+    };
+    // Double quoted values may escape "$", "\"", "\\" and "`" with a backslash
+    let mut unquoted = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.extend(chars.next()),
+            _ => unquoted.push(c),
+        }
+    }
+    return unquoted;
+}
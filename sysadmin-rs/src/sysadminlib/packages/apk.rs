@@ -1,66 +1,778 @@
 use super::*;
 
-pub static APK: PackageManager = PackageManager {
-    manager: "apk",
-    list: ListArgs {
-        command: &["list"],
-        installed: "-I",
-        available: "-a",
-        extras: "",
-        parser: parse_list,
-    },
-    info: InfoArgs {
-        command: &["info"],
-        parser: parse_info,
-    },
-    search: SearchArgs {
-        command: &["search"],
-        parser: parse_search,
-    },
-    refresh_data: RefreshArgs {
-        command: &["update"],
-        parser: parse_refresh,
-    },
-    update: UpdateArgs {
-        commands: &[&["upgrade"]],
-        fake: "",
-        parser: parse_update,
-    },
-    install: InstallArgs {
-        command: &["add"],
-        parser: parse_install,
-    },
-    uninstall: UninstallArgs {
-        command: &["del"],
-        parser: parse_uninstall,
-    },
-};
-
-fn parse_list(output: &String) -> PackagesResponse {
+pub struct Apk;
+
+pub static APK: Apk = Apk;
+
+impl PackageBackend for Apk {
+    fn manager(&self) -> &'static str {
+        return "apk";
+    }
+
+    fn list(
+        &self,
+        installed: bool,
+        available: bool,
+        extras: bool,
+        packages: &[String],
+    ) -> Result<PackagesResponse, PackagesError> {
+        let mut command = native_command("apk");
+        command.arg("list");
+        if installed {
+            command.arg("-I");
+        }
+        if available {
+            command.arg("-a");
+        }
+        if extras {
+            command.arg("-O");
+        }
+        let output = run_command(command.args(packages))?;
+        return parse_output(output, parse_list);
+    }
+
+    fn info(&self, packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(
+            native_command("apk")
+                .args(["info", "-d", "-w", "-s", "--license"])
+                .args(packages),
+        )?;
+        let mut res = parse_output(output, parse_info)?;
+        // "apk info" looks the same for packages of the repositories, the installed ones are listed
+        let installed = run_command(native_command("apk").args(["list", "-I"]).args(packages))?;
+        let installed = parse_list(&installed)?;
+        for package in res.packages.iter_mut() {
+            package.installed = installed.packages.iter().any(|p| p.name == package.name);
+        }
+        return Ok(res);
+    }
+
+    fn search(&self, search_terms: &[String]) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(
+            native_command("apk")
+                .args(["search", "-v"])
+                .args(search_terms),
+        )?;
+        return parse_output(output, parse_search);
+    }
+
+    fn refresh(&self) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(native_command("apk").arg("update"))?;
+        return parse_output(output, parse_refresh);
+    }
+
+    fn update(&self, fake: bool, dry_run: bool) -> Result<PackagesResponse, PackagesError> {
+        // "apk upgrade" can't stop after downloading, "--simulate" downloads nothing
+        if fake {
+            return Err(unsupported(self.manager(), "only download updates"));
+        }
+        let output = run_command(&mut transaction("upgrade", dry_run))?;
+        return parse_output(output, parse_update);
+    }
+
+    fn install(
+        &self,
+        packages: &[String],
+        dry_run: bool,
+    ) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(transaction("add", dry_run).args(packages))?;
+        return parse_output(output, parse_install);
+    }
+
+    fn uninstall(
+        &self,
+        packages: &[String],
+        dry_run: bool,
+    ) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(transaction("del", dry_run).args(packages))?;
+        return parse_output(output, parse_uninstall);
+    }
+
+    fn clean(&self) -> Result<PackagesResponse, PackagesError> {
+        // apk removes unused dependencies with the packages that needed them, so it has no autoremove
+        return clean_cache(native_command("apk").args(["cache", "clean"]), &["apk"]);
+    }
+
+    fn history(&self, limit: usize) -> Result<PackagesResponse, PackagesError> {
+        let output = read_log("apk.log")?;
+        let mut res = parse_output(output, parse_history)?;
+        res.transactions.truncate(limit);
+        return Ok(res);
+    }
+
+    fn repos(&self) -> Result<PackagesResponse, PackagesError> {
+        let mut res = repos::read_repos(&[etc_path(REPOSITORIES)], parse_repositories)?;
+        if res.repositories.is_empty() {
+            res.messages.push("No repositories configured".to_string());
+        }
+        return Ok(res);
+    }
+
+    fn enable_repo(&self, id: &str, enabled: bool) -> Result<PackagesResponse, PackagesError> {
+        repos::find_repo(&self.repos()?, id)?;
+        let path = etc_path(REPOSITORIES);
+        let mut contents = String::new();
+        for line in read_file(&path)?.lines() {
+            let entry = repository_entry(line).filter(|repo| repo.id == id);
+            let line = match entry {
+                Some(_) if enabled => line.trim_start_matches(['#', ' ']).to_string(),
+                Some(repo) if repo.enabled => format!("#{}", line),
+                _ => line.to_string(),
+            };
+            contents.push_str(&line);
+            contents.push('\n');
+        }
+        write_file(&path, &contents)?;
+        let message = match enabled {
+            true => format!("Enabled repository {}", id),
+            false => format!("Disabled repository {}", id),
+        };
+        return repos::changed_repo(self.repos()?, id, message);
+    }
+
+    fn add_repo(&self, repo: &Repository) -> Result<PackagesResponse, PackagesError> {
+        repos::check_no_suites(repo)?;
+        if repo.name.is_some() {
+            return Err(unsupported("apk", "name repositories"));
+        }
+        if repo.priority.is_some() {
+            return Err(unsupported("apk", "set the priority of a repository"));
+        }
+        if repo.gpgcheck == Some(false) {
+            return Err(unsupported(
+                "apk",
+                "skip the signature checks of a repository",
+            ));
+        }
+        let res = self.repos()?;
+        for url in repo.urls.iter() {
+            if res
+                .repositories
+                .iter()
+                .any(|other| other.urls.contains(url))
+            {
+                return Err(SysadminError::UnsupportedOperation {
+                    message: format!("The repository {} exists already", url),
+                });
+            }
+        }
+        let path = etc_path(REPOSITORIES);
+        let mut contents = read_file(&path)?;
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        // The id of a repository tags it, its packages are only installed when asked for like "curl@testing"
+        for url in repo.urls.iter() {
+            let disabled = if repo.enabled { "" } else { "#" };
+            match repo.id.as_str() {
+                "" => contents.push_str(&format!("{}{}\n", disabled, url)),
+                tag => contents.push_str(&format!("{}@{} {}\n", disabled, tag, url)),
+            }
+        }
+        write_file(&path, &contents)?;
+        let id = match repo.id.as_str() {
+            "" => repo.urls[0].as_str(),
+            tag => tag,
+        };
+        return repos::changed_repo(self.repos()?, id, format!("Added repository {}", id));
+    }
+
+    fn remove_repo(&self, id: &str) -> Result<PackagesResponse, PackagesError> {
+        let res = self.repos()?;
+        let path = etc_path(REPOSITORIES);
+        let mut contents = String::new();
+        for line in read_file(&path)?.lines() {
+            if repository_entry(line).is_some_and(|repo| repo.id == id) {
+                continue;
+            }
+            contents.push_str(line);
+            contents.push('\n');
+        }
+        let mut removed = repos::changed_repo(res, id, format!("Removed repository {}", id))?;
+        write_file(&path, &contents)?;
+        removed.repositories[0].file = None;
+        return Ok(removed);
+    }
+
+    fn hold(&self, packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        // apk holds a package by pinning it to a version in "/etc/apk/world", e.g. "curl=8.5.0-r0"
+        let installed = self.list(true, false, false, packages)?;
+        let mut pinned = vec![];
+        for name in packages {
+            let Some(package) = installed
+                .packages
+                .iter()
+                .find(|package| &package.name == name)
+            else {
+                return Err(SysadminError::NotFound {
+                    message: format!("{} is not installed", name),
+                });
+            };
+            pinned.push(format!(
+                "{}={}",
+                name,
+                package.version.as_deref().unwrap_or_default()
+            ));
+        }
+        let output = run_command(native_command("apk").arg("add").args(pinned))?;
+        return held(self.holds()?, "hold", packages, output);
+    }
+
+    fn unhold(&self, packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        // Adding a package without a version replaces its pin
+        let output = run_command(native_command("apk").arg("add").args(packages))?;
+        return held(self.holds()?, "unhold", packages, output);
+    }
+
+    fn holds(&self) -> Result<PackagesResponse, PackagesError> {
+        let output = read_file(&etc_path("apk/world"))?;
+        return parse_output(output, parse_world);
+    }
+
+    fn depends(&self, package: &str) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(native_command("apk").args(["info", "-R", package]))?;
+        return parse_output(output, parse_depends);
+    }
+
+    fn rdepends(&self, package: &str) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(native_command("apk").args(["info", "-r", package]))?;
+        return parse_output(output, parse_rdepends);
+    }
+
+    fn owner(&self, path: &str) -> Result<PackagesResponse, PackagesError> {
+        let output = run_query(
+            native_command("apk").args(["info", "--who-owns", path]),
+            &["Could not find owner package"],
+        )?;
+        return parse_output(output, parse_owner);
+    }
+
+    fn files(&self, package: &str) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(native_command("apk").args(["info", "-L", package]))?;
+        return parse_output(output, parse_files);
+    }
+
+    fn verify(&self, packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        // "apk audit" checks paths rather than packages
+        if !packages.is_empty() {
+            return Err(unsupported(self.manager(), "verify single packages"));
+        }
+        let output = run_verify(native_command("apk").args(["audit", "--system"]))?;
+        return parse_output(output, parse_audit);
+    }
+
+    fn user_installed(&self) -> Result<Vec<String>, PackagesError> {
+        // The world file has the packages that were asked for, e.g. "curl", "curl=8.5.0-r0" or "curl@edge"
+        let world = read_file(&etc_path("apk/world"))?;
+        return Ok(world
+            .split_whitespace()
+            .filter(|dependency| !dependency.starts_with('!'))
+            .map(|dependency| {
+                let end = dependency
+                    .find(['=', '<', '>', '~', '@'])
+                    .unwrap_or(dependency.len());
+                return dependency[..end].to_string();
+            })
+            .collect());
+    }
+}
+
+const REPOSITORIES: &str = "apk/repositories";
+
+/// Creates an apk command that changes packages, only printing what it would do with "--simulate"
+fn transaction(subcommand: &str, dry_run: bool) -> Command {
+    let mut command = native_command("apk");
+    command.arg(subcommand);
+    if dry_run {
+        command.arg("--simulate");
+    }
+    return command;
+}
+
+/// Gets the parser for the output of a subcommand, used by the fixture tests
+#[cfg(test)]
+pub(super) fn parser(subcommand: &str) -> Option<Parser> {
+    return match subcommand {
+        "list" => Some(parse_list),
+        "info" => Some(parse_info),
+        "search" => Some(parse_search),
+        "refresh" => Some(parse_refresh),
+        "update" => Some(parse_update),
+        "install" => Some(parse_install),
+        "uninstall" => Some(parse_uninstall),
+        "history" => Some(parse_history),
+        "repos" => Some(parse_repositories),
+        "holds" => Some(parse_world),
+        "depends" => Some(parse_depends),
+        "rdepends" => Some(parse_rdepends),
+        "owner" => Some(parse_owner),
+        "files" => Some(parse_files),
+        "verify" => Some(parse_audit),
+        _ => None,
+    };
+}
+
+/// Parse list output
+fn parse_list(output: &str) -> Result<PackagesResponse, PackagesError> {
     let mut res = response("list");
+    for line in output.lines() {
+        // e.g. "busybox-1.36.1-r15 x86_64 {busybox} (GPL-2.0-only) [installed]"
+        let (fields, flags) = match line.split_once(" [") {
+            Some((fields, flags)) => (fields, flags.trim_end_matches(']')),
+            None => (line, ""),
+        };
+        let parts: Vec<&str> = fields.splitn(3, ' ').collect();
+        if parts.len() < 2 {
+            continue;
+        }
+        let Some((name, version)) = split_name_version(parts[0]) else {
+            continue;
+        };
+        // The origin ("{busybox}") is the source package and is skipped
+        let license = parts
+            .get(2)
+            .and_then(|rest| rest.split_once(" ("))
+            .map(|(_, license)| license.trim_end_matches(')').to_string());
+
+        let mut installed = false;
+        let mut old_version = None;
+        if flags == "installed" {
+            installed = true;
+        }
+        if let Some(old) = flags.strip_prefix("upgradable from: ") {
+            installed = true;
+            old_version = split_name_version(old).map(|(_, version)| version.to_string());
+        }
+
+        res.packages.push(Package {
+            name: name.to_string(),
+            arch: parts[1].to_string(),
+            version: Some(version.to_string()),
+            old_version,
+            license,
+            installed,
+            ..Default::default()
+        });
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Splits an apk package string like "busybox-1.36.1-r15" into its name and version
+fn split_name_version(package: &str) -> Option<(&str, &str)> {
+    let release = package.rfind("-r")?;
+    let version = package[..release].rfind('-')?;
+    return Some((&package[..version], &package[version + 1..]));
+}
+
+/// Parse info output
+fn parse_info(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("info");
+    for section in output.split("\n\n") {
+        // e.g. "curl-8.5.0-r0 description:\nURL retrival utility and library"
+        let Some((header, value)) = section.trim_start().split_once(":\n") else {
+            continue;
+        };
+        let Some((package, field)) = header.split_once(' ') else {
+            continue;
+        };
+        let Some((name, version)) = split_name_version(package) else {
+            continue;
+        };
+        if res.packages.last().map(|p| p.name.as_str()) != Some(name) {
+            res.packages.push(Package {
+                name: name.to_string(),
+                version: Some(version.to_string()),
+                ..Default::default()
+            });
+        }
+        let package = res.packages.last_mut().unwrap();
+        let value = value.trim().to_string();
+        match field {
+            "description" => package.description = Some(value),
+            "webpage" => package.url = Some(value),
+            "license" => package.license = Some(value),
+            // apk-tools 2.14+ prints "252 KiB", older versions print plain bytes
+            "installed size" => package.size_bytes = parse_byte_size(&value),
+            _ => {}
+        }
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Parse search output
+fn parse_search(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("search");
+    for line in output.lines() {
+        // e.g. "curl-8.5.0-r0 - URL retrival utility and library"
+        let (package, description) = match line.split_once(" - ") {
+            Some((package, description)) => (package, Some(description.to_string())),
+            None => (line, None),
+        };
+        let Some((name, version)) = split_name_version(package.trim()) else {
+            continue;
+        };
+        res.packages.push(Package {
+            name: name.to_string(),
+            version: Some(version.to_string()),
+            description,
+            ..Default::default()
+        });
+    }
+    if res.packages.is_empty() {
+        res.messages.push("Nothing matched search".to_string());
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Parse refresh output
+fn parse_refresh(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("refresh");
+    res.messages.push("Updated repos".to_string());
+    for line in output.lines() {
+        if let Some(ok) = line.strip_prefix("OK: ") {
+            res.messages.push(ok.to_string());
+        }
+    }
+    return Ok(res);
+}
+
+/// Parse update output
+fn parse_update(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_transaction("update", output);
+    if res.packages.is_empty() && res.uninstalled_packages.is_empty() {
+        res.messages.push("No updates available".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parse install output
+fn parse_install(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_transaction("install", output);
+    if res.packages.is_empty() {
+        res.messages
+            .push("Package(s) already installed".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parse uninstall output
+fn parse_uninstall(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_transaction("uninstall", output);
+    if res.uninstalled_packages.is_empty() {
+        res.messages.push("No packages to uninstall".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parses the progress lines of an apk transaction, e.g.
+/// "(1/3) Upgrading musl (1.2.4-r1 -> 1.2.4-r2)" or "(2/3) Purging curl (8.5.0-r0)"
+fn parse_transaction(subcommand: &'static str, output: &str) -> PackagesResponse {
+    let mut res = response(subcommand);
+    if output.contains("fetch ") {
+        res.messages.push("Updated repos".to_string());
+    }
+    for line in output.lines() {
+        if let Some(ok) = line.strip_prefix("OK: ") {
+            res.messages.push(ok.to_string());
+            continue;
+        }
+        if !line.starts_with('(') {
+            continue;
+        }
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 4 {
+            continue;
+        }
+        let versions = line
+            .rsplit_once(" (")
+            .map(|(_, versions)| versions.trim_end_matches(')'))
+            .unwrap_or_default();
+        let (old_version, version) = match versions.split_once(" -> ") {
+            Some((old, new)) => (Some(old.to_string()), new.to_string()),
+            None => (None, versions.to_string()),
+        };
+        let mut package = Package {
+            name: parts[2].to_string(),
+            version: Some(version),
+            old_version,
+            installed: true,
+            ..Default::default()
+        };
+        match parts[1] {
+            "Installing" | "Upgrading" | "Downgrading" | "Replacing" | "Reinstalling" => {
+                res.packages.push(package)
+            }
+            "Purging" | "Removing" => {
+                package.installed = false;
+                res.uninstalled_packages.push(package);
+            }
+            _ => {}
+        }
+    }
+    if !res.packages.is_empty() {
+        res.packages_length = Some(res.packages.len());
+    }
+    if !res.uninstalled_packages.is_empty() {
+        res.uninstalled_packages_length = Some(res.uninstalled_packages.len());
+    }
     return res;
 }
 
-fn parse_info(output: &String) -> PackagesResponse {
-    return response("info");
+/// Parse "/var/log/apk.log", where apk logs every command and its progress lines, e.g.
+/// "Running `apk add curl` at 2024-04-01 10:00:00" followed by "(1/2) Installing curl (8.5.0-r0)"
+fn parse_history(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("history");
+    let mut logged: Vec<(Transaction, String)> = vec![];
+    for line in output.lines() {
+        if let Some(run) = line.strip_prefix("Running `") {
+            let Some((command, timestamp)) = run.split_once("` at ") else {
+                continue;
+            };
+            let mut transaction = Transaction::new(String::new());
+            transaction.command = Some(command.to_string());
+            transaction.timestamp = Some(timestamp.trim().to_string());
+            logged.push((transaction, String::new()));
+            continue;
+        }
+        if let Some((_, lines)) = logged.last_mut() {
+            lines.push_str(line);
+            lines.push('\n');
+        }
+    }
+    for (mut transaction, lines) in logged {
+        let changes = parse_transaction("history", &lines);
+        for package in changes.packages.iter() {
+            transaction.add_action(match package.old_version {
+                Some(_) => "upgrade",
+                None => "install",
+            });
+        }
+        if !changes.uninstalled_packages.is_empty() {
+            transaction.add_action("remove");
+        }
+        // Commands that didn't change anything, like "apk update", aren't transactions
+        if transaction.action.is_empty() {
+            continue;
+        }
+        transaction.packages = changes.packages;
+        transaction.packages.extend(changes.uninstalled_packages);
+        transaction.id = (res.transactions.len() + 1).to_string();
+        res.transactions.push(transaction);
+    }
+    res.transactions.reverse();
+    if res.transactions.is_empty() {
+        res.messages
+            .push("No transactions in the history".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parse "/etc/apk/world", the packages that were asked for, keeping the ones pinned to
+/// a version like "curl=8.5.0-r0" or to a fuzzy version like "busybox~1.36"
+fn parse_world(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("holds");
+    for dependency in output.split_whitespace() {
+        let Some(operator) = dependency.find(['=', '<', '>', '~']) else {
+            continue;
+        };
+        let version = match &dependency[operator..operator + 1] {
+            "=" | "~" => &dependency[operator + 1..],
+            // Ranges like "python3<3.12" only hold back updates past a version
+            _ => continue,
+        };
+        res.packages.push(Package {
+            name: dependency[..operator].to_string(),
+            version: Some(version.to_string()),
+            installed: true,
+            ..Default::default()
+        });
+    }
+    if res.packages.is_empty() {
+        res.messages.push("No packages are held".to_string());
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Parse "apk info -R" output, the dependencies of every package below a
+/// "curl-8.5.0-r0 depends on:" line, e.g. "ca-certificates-bundle", "so:libz.so.1" or "musl>=1.2".
+/// Virtual names like "so:libz.so.1", "cmd:curl" or "pc:zlib" are provided by packages,
+/// they aren't packages themselves
+fn parse_depends(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("depends");
+    for line in output.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.ends_with(" depends on:") || line.contains(':') {
+            continue;
+        }
+        let end = line.find(['=', '<', '>', '~']).unwrap_or(line.len());
+        if res.packages.iter().any(|p| p.name == line[..end]) {
+            continue;
+        }
+        res.packages.push(installed_package(&line[..end], None));
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Parse "apk info -r" output, the packages that need a package below a
+/// "libcurl-8.5.0-r0 is required by:" line, e.g. "curl-8.5.0-r0"
+fn parse_rdepends(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("rdepends");
+    for line in output.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.ends_with(" is required by:") {
+            continue;
+        }
+        let Some((name, version)) = split_name_version(line) else {
+            return Err(SysadminError::parse(line, "expected name-version"));
+        };
+        res.packages.push(installed_package(name, Some(version)));
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Parse "apk info --who-owns" output, e.g. "/usr/bin/curl is owned by curl-8.5.0-r0"
+fn parse_owner(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("owner");
+    for line in output.lines() {
+        let Some((path, package)) = line.split_once(" is owned by ") else {
+            continue;
+        };
+        let Some((name, version)) = split_name_version(package.trim()) else {
+            return Err(SysadminError::parse(line, "expected name-version"));
+        };
+        let mut owner = installed_package(name, Some(version));
+        owner.files.push(path.to_string());
+        res.packages.push(owner);
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Parse "apk info -L" output, the files of every package below a "curl-8.5.0-r0 contains:" line.
+/// apk prints the paths without the leading "/", e.g. "usr/bin/curl"
+fn parse_files(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("files");
+    for line in output.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(package) = line.strip_suffix(" contains:") {
+            let Some((name, version)) = split_name_version(package) else {
+                return Err(SysadminError::parse(line, "expected name-version"));
+            };
+            res.packages.push(installed_package(name, Some(version)));
+            continue;
+        }
+        let Some(package) = res.packages.last_mut() else {
+            return Err(SysadminError::parse(line, "expected a package"));
+        };
+        package.files.push(format!("/{}", line));
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
 }
 
-fn parse_search(output: &String) -> PackagesResponse {
-    return response("search");
+/// Parse "apk audit" output, a letter for what differs and the path without the leading "/", e.g.
+/// "U usr/bin/curl" for a changed file or "A usr/bin/extra" for a file no package has
+fn parse_audit(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("verify");
+    for line in output.lines() {
+        let Some((status, path)) = line.split_once(' ') else {
+            continue;
+        };
+        let problem = match status {
+            "U" => "checksum",
+            "X" => "xattrs",
+            "M" => "mode",
+            // "D" is a directory no package has
+            "A" | "D" => "added",
+            _ => continue,
+        };
+        res.findings.push(Finding {
+            path: format!("/{}", path.trim()),
+            package: None,
+            config: false,
+            problems: vec![problem.to_string()],
+        });
+    }
+    return Ok(res);
 }
 
-fn parse_refresh(output: &String) -> PackagesResponse {
-    return response("refresh");
+/// An installed package from a query of the installed packages database
+fn installed_package(name: &str, version: Option<&str>) -> Package {
+    return Package {
+        name: name.to_string(),
+        version: version.map(|version| version.to_string()),
+        installed: true,
+        ..Default::default()
+    };
 }
 
-fn parse_update(output: &String) -> PackagesResponse {
-    return response("update");
+/// Parse "/etc/apk/repositories", a repository on every line
+fn parse_repositories(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("repos");
+    res.repositories = output.lines().filter_map(repository_entry).collect();
+    return Ok(res);
 }
 
-fn parse_install(output: &String) -> PackagesResponse {
-    return response("install");
+/// Parses a line of "/etc/apk/repositories" like "https://dl-cdn.alpinelinux.org/alpine/v3.19/main",
+/// "@testing https://dl-cdn.alpinelinux.org/alpine/edge/testing" or a disabled "#/media/cdrom/apks"
+fn repository_entry(line: &str) -> Option<Repository> {
+    let line = line.trim();
+    let (enabled, line) = match line.strip_prefix('#') {
+        Some(line) => (false, line.trim_start()),
+        None => (true, line),
+    };
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let (tag, url) = match parts[..] {
+        [url] => (None, url),
+        [tag, url] if tag.starts_with('@') => (Some(&tag[1..]), url),
+        _ => return None,
+    };
+    // Other comments aren't repositories
+    if !url.contains("://") && !url.starts_with('/') {
+        return None;
+    }
+    return Some(Repository {
+        id: tag.unwrap_or(url).to_string(),
+        name: None,
+        urls: vec![url.to_string()],
+        enabled,
+        gpgcheck: None,
+        priority: None,
+        suites: vec![],
+        components: vec![],
+        file: None,
+    });
 }
 
-fn parse_uninstall(output: &String) -> PackagesResponse {
-    return response("uninstall");
+/// Parses a line apk prints while it runs, e.g. "(2/5) Installing brotli-libs (1.1.0-r1)"
+pub(super) fn parse_progress(line: &str) -> Option<ProgressEvent> {
+    if line.starts_with("fetch ") {
+        return Some(ProgressEvent::new(Phase::Refreshing, line));
+    }
+    if line.starts_with("Executing ") {
+        return Some(ProgressEvent::new(Phase::Configuring, line));
+    }
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 3 || !parts[0].starts_with('(') {
+        return None;
+    }
+    let phase = match parts[1] {
+        "Installing" => Phase::Installing,
+        "Upgrading" | "Downgrading" | "Replacing" | "Reinstalling" => Phase::Updating,
+        "Purging" | "Removing" => Phase::Removing,
+        _ => return None,
+    };
+    let mut event = ProgressEvent::new(phase, line);
+    event.package = Some(parts[2].to_string());
+    event.percent = counter_percent(parts[0]);
+    return Some(event);
 }
@@ -1,65 +1,1206 @@
 use super::*;
+use std::ops::Range;
 
-pub static APT: PackageManager = PackageManager {
-    manager: "apt",
-    list: ListArgs {
-        command: &["list"],
-        installed: "",
-        available: "",
-        extras: "",
-        parser: parse_list,
-    },
-    info: InfoArgs {
-        command: &["show"],
-        parser: parse_info,
-    },
-    search: SearchArgs {
-        command: &["search"],
-        parser: parse_search,
-    },
-    refresh_data: RefreshArgs {
-        command: &["update"],
-        parser: parse_refresh,
-    },
-    update: UpdateArgs {
-        commands: &[&["update"], &["upgrade"]],
-        fake: "",
-        parser: parse_update,
-    },
-    install: InstallArgs {
-        command: &["install"],
-        parser: parse_install,
-    },
-    uninstall: UninstallArgs {
-        command: &["remove"],
-        parser: parse_uninstall,
-    },
-};
-
-fn parse_list(output: &String) -> PackagesResponse {
-    todo!();
-}
-
-fn parse_info(output: &String) -> PackagesResponse {
-    todo!();
-}
-
-fn parse_search(output: &String) -> PackagesResponse {
-    todo!();
-}
-
-fn parse_refresh(output: &String) -> PackagesResponse {
-    todo!();
-}
-
-fn parse_update(output: &String) -> PackagesResponse {
-    todo!();
-}
-
-fn parse_install(output: &String) -> PackagesResponse {
-    todo!();
-}
-
-fn parse_uninstall(output: &String) -> PackagesResponse {
-    todo!();
+pub struct Apt;
+
+pub static APT: Apt = Apt;
+
+impl PackageBackend for Apt {
+    fn manager(&self) -> &'static str {
+        return "apt";
+    }
+
+    fn list(
+        &self,
+        installed: bool,
+        available: bool,
+        extras: bool,
+        packages: &[String],
+    ) -> Result<PackagesResponse, PackagesError> {
+        let mut command = native_command("apt");
+        command.arg("list");
+        if installed && !available && !extras {
+            command.arg("--installed");
+        }
+        let output = run_command(command.args(packages))?;
+        let mut res = parse_output(output, parse_list)?;
+        // apt has no filters for available or extra packages, they come from the flags of every package.
+        // Extra packages are installed from no repository, e.g. "foo/now 1.0 amd64 [installed,local]"
+        if available || extras {
+            res.packages.retain(|package| {
+                return (installed && package.installed)
+                    || (available && !package.installed)
+                    || (extras && package.installed && package.repository.is_none());
+            });
+            res.packages_length = Some(res.packages.len());
+        }
+        return Ok(res);
+    }
+
+    fn info(&self, packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(native_command("apt").arg("show").args(packages))?;
+        return parse_output(output, parse_info);
+    }
+
+    fn search(&self, search_terms: &[String]) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(native_command("apt").arg("search").args(search_terms))?;
+        return parse_output(output, parse_search);
+    }
+
+    fn refresh(&self) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(native_command("apt").arg("update"))?;
+        return parse_output(output, parse_refresh);
+    }
+
+    fn update(&self, fake: bool, dry_run: bool) -> Result<PackagesResponse, PackagesError> {
+        if dry_run {
+            let output = run_command(&mut simulation("upgrade"))?;
+            return parse_output(output, parse_update);
+        }
+        run_command(native_command("apt").arg("update"))?;
+        let mut command = apt_transaction();
+        command.args(["upgrade", "-y"]);
+        if fake {
+            command.arg("--download-only");
+        }
+        let output = run_command(&mut command)?;
+        let mut res = parse_output(output, parse_update)?;
+        res.messages.insert(0, "Updated repos".to_string());
+        return Ok(res);
+    }
+
+    fn install(
+        &self,
+        packages: &[String],
+        dry_run: bool,
+    ) -> Result<PackagesResponse, PackagesError> {
+        let output = match dry_run {
+            true => run_command(simulation("install").args(packages))?,
+            false => run_command(apt_transaction().args(["install", "-y"]).args(packages))?,
+        };
+        return parse_output(output, parse_install);
+    }
+
+    fn uninstall(
+        &self,
+        packages: &[String],
+        dry_run: bool,
+    ) -> Result<PackagesResponse, PackagesError> {
+        let output = match dry_run {
+            true => run_command(simulation("remove").args(packages))?,
+            false => run_command(apt_transaction().args(["remove", "-y"]).args(packages))?,
+        };
+        return parse_output(output, parse_uninstall);
+    }
+
+    fn autoremove(&self, dry_run: bool) -> Result<PackagesResponse, PackagesError> {
+        let output = match dry_run {
+            true => run_command(&mut simulation("autoremove"))?,
+            false => run_command(apt_transaction().args(["autoremove", "-y"]))?,
+        };
+        return parse_output(output, parse_autoremove);
+    }
+
+    fn depends(&self, package: &str) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(
+            native_command("apt-cache")
+                .args(["depends", "--installed"])
+                .arg(package),
+        )?;
+        return parse_output(output, parse_depends);
+    }
+
+    fn rdepends(&self, package: &str) -> Result<PackagesResponse, PackagesError> {
+        // Only packages that need the package depend on it, not the ones that recommend or break it.
+        // rdepends leaves out the kind of every relation unless it is asked for
+        let output = run_command(
+            native_command("apt-cache")
+                .args(["rdepends", "--installed"])
+                .args(IMPORTANT_RELATIONS)
+                .args(["-o", "APT::Cache::ShowDependencyType=true"])
+                .arg(package),
+        )?;
+        return parse_output(output, parse_rdepends);
+    }
+
+    fn owner(&self, path: &str) -> Result<PackagesResponse, PackagesError> {
+        let output = run_query(
+            native_command("dpkg").args(["-S", path]),
+            &["no path found matching pattern"],
+        )?;
+        let mut res = parse_output(output, parse_owner)?;
+        add_versions(&mut res);
+        return Ok(res);
+    }
+
+    fn files(&self, package: &str) -> Result<PackagesResponse, PackagesError> {
+        let output = run_query(
+            native_command("dpkg").args(["-L", package]),
+            &["is not installed"],
+        )?;
+        let mut res = parse_output(output, parse_files)?;
+        let (name, arch) = package.split_once(':').unwrap_or((package, ""));
+        for package in res.packages.iter_mut() {
+            package.name = name.to_string();
+            package.arch = arch.to_string();
+        }
+        add_versions(&mut res);
+        return Ok(res);
+    }
+
+    fn verify(&self, packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        // dpkg prints its findings in the format of rpm, it only knows the checksums of the files
+        return verify_packages(
+            "dpkg",
+            &["--verify"],
+            &["--verify"],
+            packages,
+            rpm::parse_verify,
+        );
+    }
+
+    fn user_installed(&self) -> Result<Vec<String>, PackagesError> {
+        let output = run_command(native_command("apt-mark").arg("showmanual"))?;
+        return Ok(output.lines().map(|line| line.trim().to_string()).collect());
+    }
+
+    fn clean(&self) -> Result<PackagesResponse, PackagesError> {
+        return clean_cache(native_command("apt").arg("clean"), &["apt"]);
+    }
+
+    fn history(&self, limit: usize) -> Result<PackagesResponse, PackagesError> {
+        let output = read_log("apt/history.log")?;
+        let mut res = parse_output(output, parse_history)?;
+        res.transactions.truncate(limit);
+        return Ok(res);
+    }
+
+    fn repos(&self) -> Result<PackagesResponse, PackagesError> {
+        let mut res = repos::read_repos(&[etc_path(SOURCES_LIST)], parse_sources_list)?;
+        for (extension, parser) in [
+            ("list", parse_sources_list as Parser),
+            ("sources", parse_sources),
+        ] {
+            let files = repos::config_files(SOURCES_DIR, extension)?;
+            let mut other = repos::read_repos(&files, parser)?;
+            res.repositories.append(&mut other.repositories);
+            res.out.push_str(&other.out);
+        }
+        // apt repositories have no ids, so they are named after their file and numbered if it has several
+        let files: Vec<Option<String>> = res
+            .repositories
+            .iter()
+            .map(|repo| repo.file.clone())
+            .collect();
+        for (index, repo) in res.repositories.iter_mut().enumerate() {
+            let file = repo.file.clone().unwrap_or_default();
+            let stem = Path::new(&file)
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            let number = files[..=index]
+                .iter()
+                .filter(|other| **other == repo.file)
+                .count();
+            repo.id = match files.iter().filter(|other| **other == repo.file).count() {
+                1 => stem,
+                _ => format!("{}:{}", stem, number),
+            };
+        }
+        if res.repositories.is_empty() {
+            res.messages.push("No repositories configured".to_string());
+        }
+        return Ok(res);
+    }
+
+    fn enable_repo(&self, id: &str, enabled: bool) -> Result<PackagesResponse, PackagesError> {
+        let (path, contents, entry, deb822) = find_entry(&self.repos()?, id)?;
+        let mut lines: Vec<String> = contents.lines().map(|line| line.to_string()).collect();
+        if deb822 {
+            let setting = format!("Enabled: {}", if enabled { "yes" } else { "no" });
+            match lines[entry.clone()]
+                .iter()
+                .position(|line| line.starts_with("Enabled:"))
+            {
+                Some(line) => lines[entry.start + line] = setting,
+                None => lines.insert(entry.end, setting),
+            }
+        } else {
+            let line = lines[entry.start]
+                .trim_start_matches(['#', ' ', '\t'])
+                .to_string();
+            lines[entry.start] = match enabled {
+                true => line,
+                false => format!("# {}", line),
+            };
+        }
+        write_file(&path, &(lines.join("\n") + "\n"))?;
+        let message = match enabled {
+            true => format!("Enabled repository {}", id),
+            false => format!("Disabled repository {}", id),
+        };
+        return repos::changed_repo(self.repos()?, id, message);
+    }
+
+    fn add_repo(&self, repo: &Repository) -> Result<PackagesResponse, PackagesError> {
+        let id = repos::new_id(repo);
+        repos::check_new_id(&self.repos()?, &id)?;
+        if repo.suites.is_empty() {
+            return Err(SysadminError::UnsupportedOperation {
+                message: "apt repositories need --suites".to_string(),
+            });
+        }
+        if repo.priority.is_some() {
+            return Err(unsupported("apt", "set the priority of a repository"));
+        }
+        // e.g. "Types: deb" and "URIs: https://download.docker.com/linux/debian" in "docker.sources"
+        let mut contents = String::new();
+        if let Some(name) = &repo.name {
+            contents.push_str(&format!("X-Repolib-Name: {}\n", name));
+        }
+        contents.push_str("Types: deb\n");
+        contents.push_str(&format!("URIs: {}\n", repo.urls.join(" ")));
+        contents.push_str(&format!("Suites: {}\n", repo.suites.join(" ")));
+        if !repo.components.is_empty() {
+            contents.push_str(&format!("Components: {}\n", repo.components.join(" ")));
+        }
+        if !repo.enabled {
+            contents.push_str("Enabled: no\n");
+        }
+        if repo.gpgcheck == Some(false) {
+            contents.push_str("Trusted: yes\n");
+        }
+        let path = etc_path(SOURCES_DIR).join(format!("{}.sources", id));
+        write_file(&path, &contents)?;
+        return repos::changed_repo(self.repos()?, &id, format!("Added repository {}", id));
+    }
+
+    fn remove_repo(&self, id: &str) -> Result<PackagesResponse, PackagesError> {
+        let res = self.repos()?;
+        let (path, contents, entry, deb822) = find_entry(&res, id)?;
+        let mut lines: Vec<&str> = contents.lines().collect();
+        let mut end = entry.end;
+        // The blank line after a stanza goes with it
+        if deb822 && lines.get(end).is_some_and(|line| line.trim().is_empty()) {
+            end += 1;
+        }
+        lines.drain(entry.start..end);
+        let mut contents = lines.join("\n") + "\n";
+        if entries(&contents, deb822).is_empty() && !path.ends_with(SOURCES_LIST) {
+            contents.clear();
+        }
+        write_file(&path, &contents)?;
+        let mut removed = repos::changed_repo(res, id, format!("Removed repository {}", id))?;
+        removed.repositories[0].file = None;
+        return Ok(removed);
+    }
+
+    fn hold(&self, packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(native_command("apt-mark").arg("hold").args(packages))?;
+        return held(self.holds()?, "hold", packages, output);
+    }
+
+    fn unhold(&self, packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(native_command("apt-mark").arg("unhold").args(packages))?;
+        return held(self.holds()?, "unhold", packages, output);
+    }
+
+    fn advisories(&self) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(native_command("apt").args(["list", "--upgradable"]))?;
+        return parse_output(output, parse_advisories);
+    }
+
+    fn update_advisories(
+        &self,
+        _filter: &AdvisoryFilter,
+        advisories: &[Advisory],
+        dry_run: bool,
+    ) -> Result<PackagesResponse, PackagesError> {
+        let packages = advisories
+            .iter()
+            .flat_map(|advisory| advisory.packages.iter().map(|package| &package.name));
+        let output = match dry_run {
+            true => run_command(simulation("install").arg("--only-upgrade").args(packages))?,
+            false => run_command(
+                apt_transaction()
+                    .args(["install", "--only-upgrade", "-y"])
+                    .args(packages),
+            )?,
+        };
+        return parse_output(output, parse_update);
+    }
+
+    fn holds(&self) -> Result<PackagesResponse, PackagesError> {
+        // "apt-mark showhold" only shows the names, dpkg knows the held versions as well
+        let output = run_command(native_command("dpkg-query").args([
+            "-W",
+            "-f",
+            r"${Package}\t${Architecture}\t${Version}\t${db:Status-Want}\n",
+        ]))?;
+        return parse_output(output, parse_holds);
+    }
+}
+
+/// Options of "apt-cache rdepends" that leave out the relations that aren't dependencies
+const IMPORTANT_RELATIONS: [&str; 6] = [
+    "--no-suggests",
+    "--no-recommends",
+    "--no-conflicts",
+    "--no-breaks",
+    "--no-replaces",
+    "--no-enhances",
+];
+
+const SOURCES_LIST: &str = "apt/sources.list";
+const SOURCES_DIR: &str = "apt/sources.list.d";
+
+/// Gets the parser for the output of a subcommand, used by the fixture tests
+#[cfg(test)]
+pub(super) fn parser(subcommand: &str) -> Option<Parser> {
+    return match subcommand {
+        "list" => Some(parse_list),
+        "info" => Some(parse_info),
+        "search" => Some(parse_search),
+        "refresh" => Some(parse_refresh),
+        "update" => Some(parse_update),
+        "install" => Some(parse_install),
+        "uninstall" => Some(parse_uninstall),
+        "autoremove" => Some(parse_autoremove),
+        "depends" => Some(parse_depends),
+        "rdepends" => Some(parse_rdepends),
+        "owner" => Some(parse_owner),
+        "files" => Some(parse_files),
+        "verify" => Some(rpm::parse_verify),
+        "history" => Some(parse_history),
+        "repos" => Some(parse_sources_list),
+        "holds" => Some(parse_holds),
+        "advisories" => Some(parse_advisories),
+        "repos_deb822" => Some(parse_sources),
+        _ => None,
+    };
+}
+
+/// apt command for operations that change packages, without any interactive prompts
+fn apt_transaction() -> Command {
+    let mut command = native_command("apt");
+    command.env("DEBIAN_FRONTEND", "noninteractive");
+    return command;
+}
+
+/// Creates an "apt-get -s" command, which prints the "Inst" and "Remv" lines of a transaction without doing it
+fn simulation(subcommand: &str) -> Command {
+    let mut command = native_command("apt-get");
+    command.args(["-s", subcommand]);
+    return command;
+}
+
+/// Parse list output
+fn parse_list(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("list");
+    for line in output.lines() {
+        if let Some(package) = parse_package_line(line) {
+            res.packages.push(package);
+        }
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Parses a package line as printed by "apt list" and "apt search", e.g.
+/// "curl/stable,now 7.88.1-10+deb12u8 amd64 [installed,upgradable to: 7.88.1-10+deb12u9]"
+fn parse_package_line(line: &str) -> Option<Package> {
+    let (package, flags) = match line.split_once(" [") {
+        Some((package, flags)) => (package, flags.trim_end_matches(']')),
+        None => (line, ""),
+    };
+    let parts: Vec<&str> = package.split_whitespace().collect();
+    if parts.len() != 3 || !parts[0].contains('/') {
+        return None;
+    }
+    let (name, suites) = parts[0].split_once('/').unwrap_or_default();
+    let repository = suites
+        .split(',')
+        .filter(|suite| *suite != "now")
+        .collect::<Vec<&str>>()
+        .join(",");
+
+    let mut installed = false;
+    let mut old_version = None;
+    for flag in flags.split(',') {
+        if flag == "installed" || flag.starts_with("installed ") {
+            installed = true;
+        }
+        if let Some(version) = flag.strip_prefix("upgradable from: ") {
+            installed = true;
+            old_version = Some(version.to_string());
+        }
+    }
+
+    return Some(Package {
+        name: name.to_string(),
+        arch: parts[2].to_string(),
+        version: Some(parts[1].to_string()),
+        old_version,
+        repository: if repository.is_empty() {
+            None
+        } else {
+            Some(repository)
+        },
+        installed,
+        ..Default::default()
+    });
+}
+
+/// Parse info output
+fn parse_info(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("info");
+    for stanza in output.split("\n\n") {
+        let mut package = Package::default();
+        let mut description: Vec<&str> = vec![];
+        let mut in_description = false;
+
+        for line in stanza.lines() {
+            if line.starts_with(' ') {
+                if in_description {
+                    let text = line.trim();
+                    description.push(if text == "." { "" } else { text });
+                }
+                continue;
+            }
+            in_description = false;
+            let Some((field, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match field {
+                "Package" => package.name = value.to_string(),
+                "Architecture" => package.arch = value.to_string(),
+                "Version" => package.version = Some(value.to_string()),
+                "Installed-Size" => package.size_bytes = parse_byte_size(value),
+                "Download-Size" => package.download_bytes = parse_byte_size(value),
+                "Homepage" => package.url = Some(value.to_string()),
+                "APT-Manual-Installed" => package.installed = true,
+                "APT-Sources" => {
+                    // e.g. "http://deb.debian.org/debian bookworm/main amd64 Packages"
+                    // or "/var/lib/dpkg/status" for packages that are only installed
+                    let parts: Vec<&str> = value.split_whitespace().collect();
+                    if parts.len() >= 3 {
+                        package.repository = Some(parts[1].to_string());
+                        if package.arch.is_empty() {
+                            package.arch = parts[2].to_string();
+                        }
+                    } else {
+                        package.installed = true;
+                    }
+                }
+                "Description" => {
+                    in_description = true;
+                    description.push(value);
+                }
+                _ => {}
+            }
+        }
+
+        if package.name.is_empty() {
+            continue;
+        }
+        if !description.is_empty() {
+            package.description = Some(description.join("\n").trim_end().to_string());
+        }
+        res.packages.push(package);
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Parse search output
+fn parse_search(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("search");
+    for line in output.lines() {
+        if let Some(description) = line.strip_prefix("  ") {
+            if let Some(package) = res.packages.last_mut() {
+                package.description = Some(description.trim().to_string());
+            }
+            continue;
+        }
+        if let Some(package) = parse_package_line(line) {
+            res.packages.push(package);
+        }
+    }
+    if res.packages.is_empty() {
+        res.messages.push("Nothing matched search".to_string());
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Parse refresh output
+fn parse_refresh(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("refresh");
+    res.messages.push("Updated repos".to_string());
+    for line in output.lines() {
+        if line.contains("can be upgraded") || line.contains("All packages are up to date") {
+            res.messages.push(line.trim_end_matches('.').to_string());
+        }
+    }
+    return Ok(res);
+}
+
+/// Parse update output
+fn parse_update(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_transaction("update", output);
+    if res.packages.is_empty() && res.uninstalled_packages.is_empty() {
+        res.messages.push("No updates available".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parse install output
+fn parse_install(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_transaction("install", output);
+    if res.packages.is_empty() {
+        res.messages
+            .push("Package(s) already installed".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parse uninstall output
+fn parse_uninstall(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_transaction("uninstall", output);
+    if res.uninstalled_packages.is_empty() {
+        res.messages.push("No packages to uninstall".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parse autoremove output, the same as the one of uninstall
+fn parse_autoremove(output: &str) -> Result<PackagesResponse, PackagesError> {
+    return Ok(autoremoved(parse_uninstall(output)?));
+}
+
+/// Parse "apt-cache depends" output, the dependencies are indented below the package, e.g.
+///
+/// curl
+///   Depends: libc6
+///  |Recommends: ca-certificates
+///   Depends: <libcurl4-provider>
+///     libcurl4
+///
+/// Virtual packages are in angle brackets, followed by the packages that provide them
+fn parse_depends(output: &str) -> Result<PackagesResponse, PackagesError> {
+    return Ok(parse_relations("depends", output.lines().skip(1)));
+}
+
+/// Parse "apt-cache rdepends" output with the kind of every relation, e.g.
+///
+/// libcurl4
+/// Reverse Depends:
+///   Depends: curl
+///  |Suggests: libcurl4-doc
+///   Depends: libcurl4:i386
+fn parse_rdepends(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let lines = output
+        .lines()
+        .skip_while(|line| !line.starts_with("Reverse Depends:"))
+        .skip(1);
+    return Ok(parse_relations("rdepends", lines));
+}
+
+/// Collects the packages of the Depends and PreDepends relations apt-cache prints,
+/// leaving out any other kind of relation and relations without a kind
+fn parse_relations<'a>(
+    subcommand: &'static str,
+    lines: impl Iterator<Item = &'a str>,
+) -> PackagesResponse {
+    let mut res = response(subcommand);
+    let mut kind = "";
+    for line in lines {
+        // Providers of a virtual package are indented below it, they have its kind.
+        // ": " rather than ':' keeps apart the arch of names like "libc6:i386"
+        let name = match line.trim_start_matches([' ', '|']).split_once(": ") {
+            _ if line.starts_with("    ") => line.trim(),
+            Some((current, name)) => {
+                kind = current;
+                name.trim()
+            }
+            None => {
+                kind = "";
+                line.trim()
+            }
+        };
+        if !matches!(kind, "Depends" | "PreDepends") || name.starts_with('<') {
+            continue;
+        }
+        push_dependency(&mut res, name);
+    }
+    res.packages_length = Some(res.packages.len());
+    return res;
+}
+
+/// Parse "dpkg -S" output, the packages that own a path, e.g. "libc6:amd64, libc6:i386: /usr/lib/locale".
+/// The lines of diversions like "diversion by dash from: /bin/sh" are left out
+fn parse_owner(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("owner");
+    for line in output.lines() {
+        if line.starts_with("diversion by ") {
+            continue;
+        }
+        let Some((packages, path)) = line.split_once(": ") else {
+            return Err(SysadminError::parse(line, "expected packages: path"));
+        };
+        for package in packages.split(", ") {
+            push_dependency(&mut res, package);
+            let owner = installed_package(package);
+            if let Some(owner) = res
+                .packages
+                .iter_mut()
+                .find(|p| p.name == owner.name && p.arch == owner.arch)
+            {
+                owner.files.push(path.to_string());
+            }
+        }
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Parse "dpkg -L" output, the files of a package on every line after "/.".
+/// The name of the package isn't in the output, it is set from the query
+fn parse_files(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("files");
+    let mut package = installed_package("");
+    package.files = output
+        .lines()
+        // Diversions add lines like "diverted by dash to: /usr/bin/sh.distrib"
+        .filter(|line| line.starts_with('/') && *line != "/.")
+        .map(|line| line.to_string())
+        .collect();
+    res.packages.push(package);
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Sets the installed versions and archs of packages from dpkg, leaving them
+/// as they are when dpkg-query fails
+fn add_versions(res: &mut PackagesResponse) {
+    let names: Vec<String> = res
+        .packages
+        .iter()
+        .map(|p| match p.arch.as_str() {
+            "" => p.name.clone(),
+            arch => format!("{}:{}", p.name, arch),
+        })
+        .collect();
+    let Ok(output) = run_command(
+        native_command("dpkg-query")
+            .args(["-W", "-f", r"${Package}\t${Architecture}\t${Version}\n"])
+            .args(names),
+    ) else {
+        return;
+    };
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 3 {
+            continue;
+        }
+        for package in res
+            .packages
+            .iter_mut()
+            .filter(|p| p.name == fields[0] && (p.arch.is_empty() || p.arch == fields[1]))
+        {
+            package.arch = fields[1].to_string();
+            package.version = Some(fields[2].to_string());
+        }
+    }
+}
+
+/// Adds a package of apt-cache or dpkg, like "libc6" or "libc6:i386", once
+fn push_dependency(res: &mut PackagesResponse, package: &str) {
+    let package = installed_package(package);
+    if package.name.is_empty()
+        || res
+            .packages
+            .iter()
+            .any(|p| p.name == package.name && p.arch == package.arch)
+    {
+        return;
+    }
+    res.packages.push(package);
+}
+
+/// An installed package from a query that only tells its name, like "libc6" or "libc6:i386".
+/// apt-cache is run with "--installed" and dpkg only knows installed packages
+fn installed_package(package: &str) -> Package {
+    let (name, arch) = package.split_once(':').unwrap_or((package, ""));
+    return Package {
+        name: name.to_string(),
+        arch: arch.to_string(),
+        installed: true,
+        ..Default::default()
+    };
+}
+
+/// Parses the output of a transaction ("apt upgrade", "apt install", "apt remove").
+/// apt doesn't print per package sizes in its summary, so the packages are
+/// collected from the "Get:", "Unpacking" and "Removing" progress lines instead
+fn parse_transaction(subcommand: &'static str, output: &str) -> PackagesResponse {
+    let mut res = response(subcommand);
+
+    let mut downloads: Vec<Package> = vec![];
+    let mut download_byte_size: u64 = 0;
+    for line in output.lines() {
+        // e.g. "Get:1 http://deb.debian.org/debian bookworm/main amd64 curl amd64 7.88.1-10 [315 kB]"
+        if line.starts_with("Get:") && !line.contains("Release") && !line.contains("Packages") {
+            let (fields, size) = line.split_once(" [").unwrap_or((line, ""));
+            let parts: Vec<&str> = fields.split_whitespace().collect();
+            if parts.len() < 7 {
+                continue;
+            }
+            let download_bytes = parse_byte_size(size.trim_end_matches(']'));
+            download_byte_size += download_bytes.unwrap_or_default();
+            downloads.push(Package {
+                name: parts[4].to_string(),
+                arch: parts[5].to_string(),
+                version: Some(parts[6].to_string()),
+                repository: Some(parts[2].to_string()),
+                installed: true,
+                download_bytes,
+                ..Default::default()
+            });
+        }
+    }
+
+    for line in output.lines() {
+        if let Some(rest) = line.strip_prefix("Unpacking ") {
+            // e.g. "Unpacking curl (7.88.1-10+deb12u9) over (7.88.1-10+deb12u8) ..."
+            let Some((name, version, old_version)) = parse_progress_line(rest) else {
+                continue;
+            };
+            let (name, arch) = name.split_once(':').unwrap_or((name, ""));
+            let mut package = match downloads.iter().position(|p| p.name == name) {
+                Some(i) => downloads.remove(i),
+                None => Package {
+                    name: name.to_string(),
+                    arch: arch.to_string(),
+                    installed: true,
+                    ..Default::default()
+                },
+            };
+            package.version = Some(version.to_string());
+            package.old_version = old_version.map(|v| v.to_string());
+            res.packages.push(package);
+        } else if let Some(rest) = line.strip_prefix("Removing ") {
+            // e.g. "Removing curl (7.88.1-10+deb12u8) ..."
+            let Some((name, version, _)) = parse_progress_line(rest) else {
+                continue;
+            };
+            let (name, arch) = name.split_once(':').unwrap_or((name, ""));
+            res.uninstalled_packages.push(Package {
+                name: name.to_string(),
+                arch: arch.to_string(),
+                version: Some(version.to_string()),
+                ..Default::default()
+            });
+        } else if let Some(rest) = line.strip_prefix("Inst ") {
+            // Simulated with "apt-get -s", e.g.
+            // "Inst curl [7.88.1-10+deb12u8] (7.88.1-10+deb12u9 Debian-Security:12/stable-security [amd64])"
+            res.packages.push(parse_simulated_line(rest, true));
+        } else if let Some(rest) = line.strip_prefix("Remv ") {
+            // e.g. "Remv ripgrep [13.0.0-4+b2]"
+            res.uninstalled_packages
+                .push(parse_simulated_line(rest, false));
+        } else if let Some(rest) = line.strip_prefix("After this operation, ") {
+            // e.g. "After this operation, 1,024 kB of additional disk space will be used."
+            // or "After this operation, 12.3 MB disk space will be freed."
+            let parts: Vec<&str> = rest.split_whitespace().collect();
+            if parts.len() < 2 {
+                continue;
+            }
+            let size = get_byte_size(parts[0], parts[1]);
+            if rest.contains("freed") {
+                res.uninstalled_packages_size_bytes = size;
+            } else {
+                res.packages_size_bytes = size;
+            }
+        }
+    }
+
+    // Packages that were only downloaded ("--download-only")
+    if output.contains("Download complete and in download only mode") {
+        res.messages.push("Packages downloaded only".to_string());
+        res.packages.append(&mut downloads);
+        res.packages_size_bytes = Some(download_byte_size);
+    }
+
+    if !res.packages.is_empty() {
+        res.packages_length = Some(res.packages.len());
+    }
+    if !res.uninstalled_packages.is_empty() {
+        res.uninstalled_packages_length = Some(res.uninstalled_packages.len());
+    }
+    return res;
+}
+
+/// Parses an "Inst" or "Remv" line of "apt-get -s" without its prefix, "name [installed version] (new version repository [arch])"
+fn parse_simulated_line(line: &str, installed: bool) -> Package {
+    let (name, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let current = rest
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .map(|(version, _)| version.to_string());
+    let candidate: Vec<&str> = rest
+        .split_once('(')
+        .and_then(|(_, candidate)| candidate.split_once(')'))
+        .map(|(candidate, _)| candidate.split_whitespace().collect())
+        .unwrap_or_default();
+    let (version, old_version) = match installed {
+        true => (candidate.first().map(|v| v.to_string()), current),
+        false => (current, None),
+    };
+    return Package {
+        name: name.to_string(),
+        arch: candidate
+            .get(2)
+            .map(|arch| arch.trim_matches(['[', ']']).to_string())
+            .unwrap_or_default(),
+        version,
+        old_version,
+        repository: candidate.get(1).map(|repository| repository.to_string()),
+        installed,
+        ..Default::default()
+    };
+}
+
+/// Splits "name (version) over (old_version) ..." into its parts
+fn parse_progress_line(line: &str) -> Option<(&str, &str, Option<&str>)> {
+    let (name, rest) = line.split_once(" (")?;
+    let (version, rest) = rest.split_once(')')?;
+    let old_version = rest
+        .strip_prefix(" over (")
+        .and_then(|old| old.split_once(')'))
+        .map(|(old, _)| old);
+    return Some((name, version, old_version));
+}
+
+/// Parses a line apt prints while it runs, e.g.
+/// "Get:2 http://deb.debian.org/debian bookworm/main amd64 ripgrep amd64 13.0.0-4+b2 [1,446 kB]" or "Setting up ripgrep (13.0.0-4+b2) ..."
+pub(super) fn parse_progress(line: &str) -> Option<ProgressEvent> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let first = parts.first()?;
+    if first.starts_with("Get:") {
+        // Packages have "name arch version" after the suite, the lists of "apt update" don't
+        let Some((_, size)) = line.rsplit_once(" [") else {
+            return Some(ProgressEvent::new(Phase::Refreshing, line));
+        };
+        let mut event = ProgressEvent::new(Phase::Downloading, line);
+        if parts.len() >= 8 {
+            event.package = Some(parts[4].to_string());
+        } else {
+            event.phase = Phase::Refreshing;
+        }
+        event.download_bytes = parse_byte_size(size.trim_end_matches(']'));
+        return Some(event);
+    }
+    if first.starts_with("Hit:") || first.starts_with("Ign:") {
+        return Some(ProgressEvent::new(Phase::Refreshing, line));
+    }
+    let (phase, package) = match parts.as_slice() {
+        ["Unpacking", package, .., "over", _, _] => (Phase::Updating, package),
+        ["Unpacking", package, ..] => (Phase::Installing, package),
+        ["Setting", "up", package, ..] => (Phase::Configuring, package),
+        ["Removing", package, ..] => (Phase::Removing, package),
+        _ => return None,
+    };
+    let mut event = ProgressEvent::new(phase, line);
+    // e.g. "libpcre2-8-0:amd64"
+    event.package = package.split(':').next().map(|name| name.to_string());
+    return Some(event);
+}
+
+/// Parse "/var/log/apt/history.log", a paragraph for every transaction, e.g.
+/// "Start-Date: 2024-04-01  10:00:00", "Requested-By: alice (1000)" and
+/// "Upgrade: curl:amd64 (7.88.1-10+deb12u8, 7.88.1-10+deb12u9)"
+fn parse_history(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("history");
+    for paragraph in output.split("\n\n") {
+        let mut transaction = Transaction::new((res.transactions.len() + 1).to_string());
+        for line in paragraph.lines() {
+            let Some((key, value)) = line.split_once(": ") else {
+                continue;
+            };
+            let value = value.trim();
+            let action = match key {
+                "Start-Date" => {
+                    transaction.timestamp =
+                        Some(value.split_whitespace().collect::<Vec<_>>().join(" "));
+                    continue;
+                }
+                "Commandline" => {
+                    transaction.command = Some(value.to_string());
+                    continue;
+                }
+                // e.g. "alice (1000)"
+                "Requested-By" => {
+                    transaction.user = value.split_whitespace().next().map(|user| user.to_string());
+                    continue;
+                }
+                "Install" => "install",
+                "Reinstall" => "reinstall",
+                "Upgrade" => "upgrade",
+                "Downgrade" => "downgrade",
+                "Remove" | "Purge" => "remove",
+                _ => continue,
+            };
+            transaction.add_action(action);
+            for (name, versions) in history_packages(value) {
+                let versions: Vec<&str> = versions
+                    .split(", ")
+                    .filter(|version| *version != "automatic")
+                    .collect();
+                // Upgrades and downgrades have the old and the new version
+                let (old_version, version) = match versions.as_slice() {
+                    [old, new] => (Some(old.to_string()), new.to_string()),
+                    _ => (
+                        None,
+                        versions.first().copied().unwrap_or_default().to_string(),
+                    ),
+                };
+                let (name, arch) = name.split_once(':').unwrap_or((name, ""));
+                transaction.packages.push(Package {
+                    name: name.to_string(),
+                    arch: arch.to_string(),
+                    version: Some(version),
+                    old_version,
+                    installed: action != "remove",
+                    ..Default::default()
+                });
+            }
+        }
+        if transaction.timestamp.is_some() {
+            res.transactions.push(transaction);
+        }
+    }
+    res.transactions.reverse();
+    if res.transactions.is_empty() {
+        res.messages
+            .push("No transactions in the history".to_string());
+    }
+    return Ok(res);
+}
+
+/// Splits a package list of the history like "libpcre2-8-0:amd64 (10.42-1, automatic), ripgrep:amd64 (13.0.0-4+b2)"
+/// into the names and the text in their parentheses
+fn history_packages(list: &str) -> Vec<(&str, &str)> {
+    let mut packages = vec![];
+    let mut rest = list;
+    while let Some((name, after)) = rest.split_once(" (") {
+        let Some((versions, after)) = after.split_once(')') else {
+            break;
+        };
+        packages.push((name.trim_start_matches(", ").trim(), versions));
+        rest = after;
+    }
+    return packages;
+}
+
+/// Parse the upgradable packages of "apt list --upgradable" into an advisory for every update from a
+/// security suite, e.g. "curl/bookworm-security 7.88.1-10+deb12u9 amd64 [upgradable from: 7.88.1-10+deb12u8]"
+fn parse_advisories(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("advisories");
+    for package in output.lines().filter_map(parse_package_line) {
+        let repository = package.repository.clone().unwrap_or_default();
+        let Some(suite) = repository
+            .split(',')
+            .find(|suite| suite.ends_with("-security"))
+        else {
+            continue;
+        };
+        res.advisories.push(Advisory {
+            id: format!("{}/{}", package.name, suite),
+            kind: Some("security".to_string()),
+            severity: None,
+            title: None,
+            issued: None,
+            cves: vec![],
+            packages: vec![package],
+        });
+    }
+    return Ok(res);
+}
+
+/// Parse the installed packages from dpkg-query, e.g. "curl\tamd64\t7.88.1-10+deb12u8\thold", keeping the held ones
+fn parse_holds(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("holds");
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 4 {
+            return Err(SysadminError::parse(line, "expected 4 fields"));
+        }
+        if fields[3] != "hold" {
+            continue;
+        }
+        res.packages.push(Package {
+            name: fields[0].to_string(),
+            arch: fields[1].to_string(),
+            version: Some(fields[2].to_string()).filter(|version| !version.is_empty()),
+            // Packages can be held before they are installed
+            installed: !fields[2].is_empty(),
+            ..Default::default()
+        });
+    }
+    if res.packages.is_empty() {
+        res.messages.push("No packages are held".to_string());
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Finds the entry of a repository in its file, returns the file, its contents,
+/// the lines of the entry and whether the file has the deb822 format
+fn find_entry(
+    res: &PackagesResponse,
+    id: &str,
+) -> Result<(PathBuf, String, Range<usize>, bool), PackagesError> {
+    let repo = repos::find_repo(res, id)?;
+    let index = res
+        .repositories
+        .iter()
+        .filter(|other| other.file == repo.file)
+        .position(|other| other.id == id)
+        .unwrap_or_default();
+    let path = PathBuf::from(repo.file.unwrap_or_default());
+    let deb822 = path
+        .extension()
+        .is_some_and(|extension| extension == "sources");
+    let contents = read_file(&path)?;
+    let Some((entry, _)) = entries(&contents, deb822).into_iter().nth(index) else {
+        return Err(SysadminError::NotFound {
+            message: format!("No repository with the id {}", id),
+        });
+    };
+    return Ok((path, contents, entry, deb822));
+}
+
+/// Parse "/etc/apt/sources.list" and ".list" files, e.g. "deb [signed-by=/usr/share/keyrings/docker.gpg] https://download.docker.com/linux/debian bookworm stable".
+/// Commented out entries are disabled repositories
+fn parse_sources_list(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("repos");
+    res.repositories = entries(output, false)
+        .into_iter()
+        .map(|(_, repo)| repo)
+        .collect();
+    return Ok(res);
+}
+
+/// Parse ".sources" files in the deb822 format, a stanza for every repository, e.g.
+/// "Types: deb", "URIs: https://deb.debian.org/debian", "Suites: bookworm bookworm-updates" and "Components: main"
+fn parse_sources(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("repos");
+    res.repositories = entries(output, true)
+        .into_iter()
+        .map(|(_, repo)| repo)
+        .collect();
+    return Ok(res);
+}
+
+/// Gets the repositories of a sources file with the lines they are on
+fn entries(contents: &str, deb822: bool) -> Vec<(Range<usize>, Repository)> {
+    let lines: Vec<&str> = contents.lines().collect();
+    if !deb822 {
+        return lines
+            .iter()
+            .enumerate()
+            .filter_map(|(number, line)| Some((number..number + 1, one_line_entry(line)?)))
+            .collect();
+    }
+    let mut entries = vec![];
+    let mut start = 0;
+    for number in 0..=lines.len() {
+        if number < lines.len() && !lines[number].trim().is_empty() {
+            continue;
+        }
+        if let Some(repo) = stanza_entry(&lines[start..number]) {
+            entries.push((start..number, repo));
+        }
+        start = number + 1;
+    }
+    return entries;
+}
+
+fn one_line_entry(line: &str) -> Option<Repository> {
+    let line = line.trim();
+    let (enabled, line) = match line.strip_prefix('#') {
+        Some(line) => (false, line.trim_start()),
+        None => (true, line),
+    };
+    let (kind, rest) = line.split_once(char::is_whitespace)?;
+    if kind != "deb" && kind != "deb-src" {
+        return None;
+    }
+    let mut rest = rest.trim_start();
+    let mut trusted = false;
+    if let Some(options) = rest.strip_prefix('[') {
+        let (options, after) = options.split_once(']')?;
+        trusted = options
+            .split_whitespace()
+            .any(|option| option == "trusted=yes");
+        rest = after.trim_start();
+    }
+    // CD-ROMs have spaces in their URL, e.g. "cdrom:[Debian GNU/Linux 12.5.0 _Bookworm_]/"
+    let label_end = match rest.starts_with("cdrom:[") {
+        true => rest.find(']')?,
+        false => 0,
+    };
+    let url_end = label_end + rest[label_end..].find(char::is_whitespace)?;
+    let mut parts = rest[url_end..].split_whitespace();
+    return Some(Repository {
+        id: String::new(),
+        name: None,
+        urls: vec![rest[..url_end].to_string()],
+        enabled,
+        gpgcheck: Some(!trusted),
+        priority: None,
+        suites: vec![parts.next()?.to_string()],
+        components: parts.map(|component| component.to_string()).collect(),
+        file: None,
+    });
+}
+
+fn stanza_entry(lines: &[&str]) -> Option<Repository> {
+    let mut repo = Repository {
+        id: String::new(),
+        name: None,
+        urls: vec![],
+        enabled: true,
+        gpgcheck: Some(true),
+        priority: None,
+        suites: vec![],
+        components: vec![],
+        file: None,
+    };
+    for line in lines {
+        // Comments and continuation lines, like the ones of an inline "Signed-By" key
+        if line.starts_with('#') || line.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let values = value.split_whitespace().map(|value| value.to_string());
+        match key.trim() {
+            "X-Repolib-Name" => repo.name = Some(value.trim().to_string()),
+            "URIs" => repo.urls = values.collect(),
+            "Suites" => repo.suites = values.collect(),
+            "Components" => repo.components = values.collect(),
+            "Enabled" => repo.enabled = value.trim() != "no",
+            "Trusted" => repo.gpgcheck = Some(value.trim() != "yes"),
+            _ => {}
+        }
+    }
+    if repo.urls.is_empty() {
+        return None;
+    }
+    return Some(repo);
 }
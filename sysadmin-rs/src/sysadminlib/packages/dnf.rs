@@ -1,350 +1,1109 @@
 use super::*;
-use regex::Regex;
-
-pub static DNF: PackageManager = PackageManager {
-    manager: "dnf",
-    list: ListArgs {
-        command: &["list"],
-        installed: "--installed",
-        available: "--available",
-        extras: "--extras",
-        parser: parse_list,
-    },
-    info: InfoArgs {
-        command: &["info"],
-        parser: parse_info,
-    },
-    search: SearchArgs {
-        command: &["search"],
-        parser: parse_search,
-    },
-    refresh_data: RefreshArgs {
-        command: &["check-upgrade"],
-        parser: parse_refresh,
-    },
-    update: UpdateArgs {
-        commands: &[&["upgrade", "-y"]],
-        fake: "--downloadonly",
-        parser: parse_update,
-    },
-    install: InstallArgs {
-        command: &["install", "-y"],
-        parser: parse_install,
-    },
-    uninstall: UninstallArgs {
-        command: &["remove", "-y"],
-        parser: parse_uninstall,
-    },
-};
-
-/// Parse list output
-fn parse_list(output: &String) -> PackagesResponse {
-    let mut res = response("list");
-    let mut lines;
-    if output.contains("Repositories loaded.") {
-        res.messages.push("Updated repos".to_string());
+use std::sync::OnceLock;
+
+pub struct Dnf;
+
+pub static DNF: Dnf = Dnf;
+
+/// Directories of the ".repo" files, new repositories are added to the first one
+const REPO_DIRS: [&str; 2] = ["yum.repos.d", "distro.repos.d"];
+
+/// Major version of dnf, dnf5 renamed some of the repoquery tags
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum DnfVersion {
+    Dnf4,
+    Dnf5,
+}
+
+/// Output of "dnf --version", e.g. "dnf5 version 5.2.12.0" on dnf5 or "4.19.2" on dnf4
+fn version_output() -> &'static str {
+    static OUTPUT: OnceLock<String> = OnceLock::new();
+    return OUTPUT.get_or_init(|| match native_command("dnf").arg("--version").output() {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).to_string(),
+        Err(_) => String::new(),
+    });
+}
+
+/// Detects the dnf version from "dnf --version", which starts with "dnf5" on dnf5
+fn dnf_version() -> DnfVersion {
+    return match version_output().starts_with("dnf5") {
+        true => DnfVersion::Dnf5,
+        false => DnfVersion::Dnf4,
+    };
+}
+
+/// Whether repoquery can print its packages as json, dnf5 can since 5.2.12
+fn json_repoquery() -> bool {
+    let Some(version) = version_output()
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("dnf5 version "))
+    else {
+        return false;
+    };
+    let numbers: Vec<u32> = version
+        .trim()
+        .split('.')
+        .map_while(|number| number.parse().ok())
+        .collect();
+    return numbers >= vec![5, 2, 12];
+}
+
+/// Separates the records of a queryformat, descriptions can span multiple lines
+const RECORD_SEPARATOR: char = '\x1e';
+
+/// Builds a repoquery queryformat with tab separated fields and the given tag for the description
+fn query_format(version: DnfVersion, description: &str) -> String {
+    let tags = match version {
+        DnfVersion::Dnf4 => ["repoid", "installsize", "downloadsize"],
+        DnfVersion::Dnf5 => ["repo_id", "install_size", "download_size"],
+    };
+    return format!(
+        "%{{name}}\t%{{epoch}}\t%{{version}}\t%{{release}}\t%{{arch}}\t%{{{}}}\t%{{from_repo}}\t%{{{}}}\t%{{{}}}\t%{{url}}\t%{{license}}\t%{{{}}}{}",
+        tags[0], tags[1], tags[2], description, RECORD_SEPARATOR
+    );
+}
+
+/// Runs "dnf repoquery" with the given filters and parses its records,
+/// the json ones where dnf has them and the ones of query_format() otherwise
+fn repoquery(
+    subcommand: &'static str,
+    filters: &[&str],
+    description: &str,
+    packages: &[String],
+) -> Result<PackagesResponse, PackagesError> {
+    if json_repoquery() {
+        let output = run_command(
+            native_command("dnf")
+                .args(["repoquery", "--quiet", "--json"])
+                .args(filters)
+                .args(packages),
+        )?;
+        let mut res = parse_json_records(subcommand, description, &output)?;
+        res.out = output;
+        return Ok(res);
     }
+    let output = run_command(
+        native_command("dnf")
+            .args(["repoquery", "--quiet", "--queryformat"])
+            .arg(query_format(dnf_version(), description))
+            .args(filters)
+            .args(packages),
+    )?;
+    let mut res = parse_records(subcommand, &output)?;
+    res.out = output;
+    return Ok(res);
+}
 
-    let stops = ["Installed packages", "Available packages", "Extra packages"];
-    for stop in stops {
-        lines = output.lines();
-        while !lines.next().unwrap_or(stop).contains(stop) {}
-        loop {
-            let line = lines.next().unwrap_or_default();
-            if line.is_empty() {
-                break;
-            }
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            let name_and_arch: Vec<&str> = parts[0].split(".").collect();
-            let installed = stop != "Available packages";
-            res.packages.push(Package {
-                name: name_and_arch[0].to_string(),
-                arch: name_and_arch[1].to_string(),
-                version: Some(
-                    parts[1].to_string().split("-").collect::<Vec<&str>>()[0].to_string(),
-                ),
-                old_version: None,
-                repository: Some(parts[2].to_string()),
-                size: None,
-                download: None,
-                description: None,
-                url: None,
-                license: None,
-                installed,
-            })
+impl PackageBackend for Dnf {
+    fn manager(&self) -> &'static str {
+        return "dnf";
+    }
+
+    fn list(
+        &self,
+        installed: bool,
+        available: bool,
+        extras: bool,
+        packages: &[String],
+    ) -> Result<PackagesResponse, PackagesError> {
+        let mut res = response("list");
+        // Like "dnf list", list installed and available packages without any filter
+        let all = !installed && !available && !extras;
+        if installed || all {
+            append(
+                &mut res,
+                repoquery("list", &["--installed"], "summary", packages)?,
+            );
+        }
+        if available || all {
+            let mut available = repoquery("list", &["--available"], "summary", packages)?;
+            available.packages.retain(|package| {
+                !res.packages.iter().any(|installed| {
+                    installed.name == package.name
+                        && installed.arch == package.arch
+                        && installed.version == package.version
+                })
+            });
+            append(&mut res, available);
+        }
+        if extras {
+            append(
+                &mut res,
+                repoquery("list", &["--extras"], "summary", packages)?,
+            );
+        }
+        res.packages_length = Some(res.packages.len());
+        return Ok(res);
+    }
+
+    fn info(&self, packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        let mut res = repoquery("info", &["--installed"], "description", packages)?;
+        let available = repoquery(
+            "info",
+            &["--available", "--latest-limit", "1"],
+            "description",
+            packages,
+        )?;
+        append(&mut res, available);
+        res.packages_length = Some(res.packages.len());
+        return Ok(res);
+    }
+
+    fn search(&self, search_terms: &[String]) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(native_command("dnf").arg("search").args(search_terms))?;
+        return parse_output(output, parse_search);
+    }
+
+    fn refresh(&self) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(native_command("dnf").args(["makecache", "--refresh"]))?;
+        return parse_output(output, parse_refresh);
+    }
+
+    fn update(&self, fake: bool, dry_run: bool) -> Result<PackagesResponse, PackagesError> {
+        if dry_run {
+            let output = run_assumeno(native_command("dnf").args(["upgrade", "--assumeno"]))?;
+            return parse_output(output, parse_update);
+        }
+        let mut command = native_command("dnf");
+        command.args(["upgrade", "-y"]);
+        if fake {
+            command.arg("--downloadonly");
+        }
+        let output = run_command(&mut command)?;
+        let mut res = parse_output(output, parse_update)?;
+        if !fake {
+            add_installed_details(&mut res);
+        }
+        return Ok(res);
+    }
+
+    fn install(
+        &self,
+        packages: &[String],
+        dry_run: bool,
+    ) -> Result<PackagesResponse, PackagesError> {
+        if dry_run {
+            let output = run_assumeno(
+                native_command("dnf")
+                    .args(["install", "--assumeno"])
+                    .args(packages),
+            )?;
+            return parse_output(output, parse_install);
+        }
+        let output = run_command(native_command("dnf").args(["install", "-y"]).args(packages))?;
+        let mut res = parse_output(output, parse_install)?;
+        add_installed_details(&mut res);
+        return Ok(res);
+    }
+
+    fn uninstall(
+        &self,
+        packages: &[String],
+        dry_run: bool,
+    ) -> Result<PackagesResponse, PackagesError> {
+        let output = match dry_run {
+            true => run_assumeno(
+                native_command("dnf")
+                    .args(["remove", "--assumeno"])
+                    .args(packages),
+            )?,
+            false => run_command(native_command("dnf").args(["remove", "-y"]).args(packages))?,
+        };
+        return parse_output(output, parse_uninstall);
+    }
+
+    fn history(&self, limit: usize) -> Result<PackagesResponse, PackagesError> {
+        let list = run_command(native_command("dnf").args(["history", "list"]))?;
+        // e.g. "     3 | install ripgrep  | 2024-04-01 10:00 | Install  |    1" on dnf4,
+        // "3 dnf install ripgrep  2024-04-01 10:00:00  1" on dnf5, the newest first
+        let ids: Vec<&str> = list
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .filter(|id| id.chars().all(|c| c.is_ascii_digit()))
+            .take(limit)
+            .collect();
+        let mut output = String::new();
+        for id in ids {
+            output.push_str(&run_command(
+                native_command("dnf").args(["history", "info", id]),
+            )?);
+            output.push('\n');
+        }
+        return parse_output(output, parse_history);
+    }
+
+    fn undo(&self, id: &str) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(native_command("dnf").args(["history", "undo", "-y", id]))?;
+        return parse_output(output, parse_undo);
+    }
+
+    fn repos(&self) -> Result<PackagesResponse, PackagesError> {
+        return repos::ini_repos(&REPO_DIRS);
+    }
+
+    fn enable_repo(&self, id: &str, enabled: bool) -> Result<PackagesResponse, PackagesError> {
+        return repos::ini_enable(&REPO_DIRS, id, enabled);
+    }
+
+    fn add_repo(&self, repo: &Repository) -> Result<PackagesResponse, PackagesError> {
+        return repos::ini_add(&REPO_DIRS, repo);
+    }
+
+    fn remove_repo(&self, id: &str) -> Result<PackagesResponse, PackagesError> {
+        return repos::ini_remove(&REPO_DIRS, id);
+    }
+
+    fn hold(&self, packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(
+            native_command("dnf")
+                .args(["versionlock", "add"])
+                .args(packages),
+        )?;
+        return held(self.holds()?, "hold", packages, output);
+    }
+
+    fn unhold(&self, packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(
+            native_command("dnf")
+                .args(["versionlock", "delete"])
+                .args(packages),
+        )?;
+        return held(self.holds()?, "unhold", packages, output);
+    }
+
+    fn holds(&self) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(native_command("dnf").args(["versionlock", "list"]))?;
+        return parse_output(output, parse_holds);
+    }
+
+    fn advisories(&self) -> Result<PackagesResponse, PackagesError> {
+        // The CVEs are only listed in place of the advisory ids, so both lists are parsed together
+        let mut output = run_command(native_command("dnf").args(["updateinfo", "list"]))?;
+        output.push_str(&run_command(native_command("dnf").args([
+            "updateinfo",
+            "list",
+            "--with-cve",
+        ]))?);
+        return parse_output(output, parse_advisories);
+    }
+
+    fn update_advisories(
+        &self,
+        filter: &AdvisoryFilter,
+        _advisories: &[Advisory],
+        dry_run: bool,
+    ) -> Result<PackagesResponse, PackagesError> {
+        let mut command = native_command("dnf");
+        command.arg("upgrade");
+        match filter {
+            AdvisoryFilter::Security => command.arg("--security"),
+            AdvisoryFilter::Id(id) => command.arg(format!("--advisories={}", id)),
+        };
+        let output = match dry_run {
+            true => run_assumeno(command.arg("--assumeno"))?,
+            false => run_command(command.arg("-y"))?,
+        };
+        return parse_output(output, parse_update);
+    }
+
+    fn autoremove(&self, dry_run: bool) -> Result<PackagesResponse, PackagesError> {
+        let output = match dry_run {
+            true => run_assumeno(native_command("dnf").args(["autoremove", "--assumeno"]))?,
+            false => run_command(native_command("dnf").args(["autoremove", "-y"]))?,
+        };
+        return parse_output(output, parse_autoremove);
+    }
+
+    fn depends(&self, package: &str) -> Result<PackagesResponse, PackagesError> {
+        // dnf5 dropped "--resolve", it resolves the requirements to packages with "--providers-of"
+        let filters: &[&str] = match dnf_version() {
+            DnfVersion::Dnf4 => &["--installed", "--requires", "--resolve"],
+            DnfVersion::Dnf5 => &["--installed", "--providers-of=requires"],
+        };
+        return repoquery("depends", filters, "summary", &[package.to_string()]);
+    }
+
+    fn rdepends(&self, package: &str) -> Result<PackagesResponse, PackagesError> {
+        return repoquery(
+            "rdepends",
+            &["--installed", "--whatrequires"],
+            "summary",
+            &[package.to_string()],
+        );
+    }
+
+    fn user_installed(&self) -> Result<Vec<String>, PackagesError> {
+        let res = repoquery("list", &["--userinstalled"], "summary", &[])?;
+        return Ok(res
+            .packages
+            .into_iter()
+            .map(|package| package.name)
+            .collect());
+    }
+
+    fn owner(&self, path: &str) -> Result<PackagesResponse, PackagesError> {
+        return rpm::owner(path);
+    }
+
+    fn files(&self, package: &str) -> Result<PackagesResponse, PackagesError> {
+        return rpm::files(package);
+    }
+
+    fn verify(&self, packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        return rpm::verify(packages);
+    }
+
+    fn clean(&self) -> Result<PackagesResponse, PackagesError> {
+        // dnf5 keeps its cache in "/var/cache/libdnf5"
+        return clean_cache(
+            native_command("dnf").args(["clean", "all"]),
+            &["dnf", "libdnf5"],
+        );
+    }
+}
+
+/// Gets the parser for the output of a subcommand, used by the fixture tests
+#[cfg(test)]
+pub(super) fn parser(subcommand: &str) -> Option<Parser> {
+    return match subcommand {
+        "list" => Some(|output| parse_records("list", output)),
+        "info" => Some(|output| parse_records("info", output)),
+        "json_list" => Some(|output| parse_json_records("list", "summary", output)),
+        "json_info" => Some(|output| parse_json_records("info", "description", output)),
+        "search" => Some(parse_search),
+        "refresh" => Some(parse_refresh),
+        "update" => Some(parse_update),
+        "install" => Some(parse_install),
+        "uninstall" => Some(parse_uninstall),
+        "autoremove" => Some(parse_autoremove),
+        "repos" => Some(repos::parse_ini),
+        "history" => Some(parse_history),
+        "undo" => Some(parse_undo),
+        "holds" => Some(parse_holds),
+        "advisories" => Some(parse_advisories),
+        _ => None,
+    };
+}
+
+/// Adds the packages and output of another response to a response
+fn append(res: &mut PackagesResponse, mut other: PackagesResponse) {
+    res.packages.append(&mut other.packages);
+    res.out.push_str(&other.out);
+}
+
+/// Parses records of a repoquery with the format from query_format()
+fn parse_records(
+    subcommand: &'static str,
+    output: &str,
+) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response(subcommand);
+    for record in output.split(RECORD_SEPARATOR) {
+        let record = record.trim_start_matches('\n');
+        if record.trim().is_empty() {
+            continue;
         }
+        let fields: Vec<&str> = record.splitn(12, '\t').collect();
+        if fields.len() != 12 {
+            return Err(SysadminError::parse(record, "expected 12 fields"));
+        }
+        let installed = fields[5] == "@System";
+        let repository = match (installed, fields[6]) {
+            (true, from_repo) if !from_repo.is_empty() => from_repo,
+            _ => fields[5],
+        };
+        res.packages.push(Package {
+            name: fields[0].to_string(),
+            arch: fields[4].to_string(),
+            version: Some(evr(fields[1], fields[2], fields[3])),
+            repository: Some(repository.to_string()),
+            description: optional(fields[11].trim_end()),
+            url: optional(fields[9]),
+            license: optional(fields[10]),
+            installed,
+            size_bytes: fields[7].parse().ok(),
+            download_bytes: match installed {
+                true => None,
+                false => fields[8].parse().ok(),
+            },
+            ..Default::default()
+        });
     }
     res.packages_length = Some(res.packages.len());
-    return res;
+    return Ok(res);
 }
 
-/// Parse info output
-fn parse_info(output: &String) -> PackagesResponse {
-    let mut res = response("info");
-
-    let field_split = Regex::new(r"\n\S").unwrap();
-    let parts = output.split("\n\n");
-    let mut installed = false;
-    for mut part in parts {
-        if part.starts_with("Installed packages\n") {
-            installed = true;
-            part = &part[19..];
-        }
-        if part.starts_with("Available packages\n") {
-            installed = false;
-            part = &part[19..]
-        }
-        let fields: Vec<&str> = field_split.split(part).collect();
-        if installed {
-            res.packages.push(Package {
-                name: clean(fields[0]),
-                arch: clean(fields[4]),
-                version: Some(clean(fields[2])),
-                old_version: None,
-                repository: Some(clean(fields[7])),
-                size: Some(clean(fields[5])),
-                download: None,
-                description: Some(clean(fields[11])),
-                url: Some(clean(fields[9])),
-                license: Some(clean(fields[10])),
-                installed,
-            });
-        } else {
-            res.packages.push(Package {
-                name: clean(fields[0]),
-                arch: clean(fields[4]),
-                version: Some(clean(fields[2])),
-                old_version: None,
-                repository: Some(clean(fields[8])),
-                size: Some(clean(fields[6])),
-                download: Some(clean(fields[5])),
-                description: Some(clean(fields[12])),
-                url: Some(clean(fields[10])),
-                license: Some(clean(fields[11])),
-                installed,
-            });
+/// Parses the packages of "dnf repoquery --json", an array of objects with the queryformat tags, e.g.
+///
+/// [{"name": "curl", "epoch": "0", "version": "8.6.0", "release": "4.fc40", "arch": "x86_64",
+///   "repo_id": "@System", "from_repo": "updates", "install_size": 789234, ...}]
+///
+/// The description is the value of the given tag, "summary" or "description"
+fn parse_json_records(
+    subcommand: &'static str,
+    description: &str,
+    output: &str,
+) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response(subcommand);
+    let records: Vec<serde_json::Value> = match serde_json::from_str(output.trim()) {
+        Ok(records) => records,
+        Err(error) => {
+            let line = output.lines().next().unwrap_or_default();
+            return Err(SysadminError::parse(line, &error.to_string()));
         }
+    };
+    for record in records {
+        // Numbers like the epoch and the sizes are strings or numbers depending on the dnf5 version
+        let field = |tag: &str| -> String {
+            return match &record[tag] {
+                serde_json::Value::String(value) => value.clone(),
+                serde_json::Value::Number(value) => value.to_string(),
+                _ => String::new(),
+            };
+        };
+        let name = field("name");
+        if name.is_empty() {
+            return Err(SysadminError::parse(&record.to_string(), "expected a name"));
+        }
+        let installed = field("repo_id") == "@System";
+        let repository = match (installed, field("from_repo")) {
+            (true, from_repo) if !from_repo.is_empty() => from_repo,
+            _ => field("repo_id"),
+        };
+        res.packages.push(Package {
+            name,
+            arch: field("arch"),
+            version: Some(evr(&field("epoch"), &field("version"), &field("release"))),
+            repository: Some(repository),
+            description: optional(field(description).trim_end()),
+            url: optional(&field("url")),
+            license: optional(&field("license")),
+            installed,
+            size_bytes: field("install_size").parse().ok(),
+            download_bytes: match installed {
+                true => None,
+                false => field("download_size").parse().ok(),
+            },
+            ..Default::default()
+        });
     }
     res.packages_length = Some(res.packages.len());
-    return res;
+    return Ok(res);
+}
+
+/// Formats a version as "epoch:version-release", leaving out an epoch of 0
+fn evr(epoch: &str, version: &str, release: &str) -> String {
+    if epoch.is_empty() || epoch == "0" || epoch == "(none)" {
+        return format!("{}-{}", version, release);
+    }
+    return format!("{}:{}-{}", epoch, version, release);
 }
 
-fn clean(to_clean: &str) -> String {
-    let clean = Regex::new(r"[\S ]*: | +:").unwrap();
-    return clean.replace_all(to_clean, "").to_string();
+fn optional(value: &str) -> Option<String> {
+    return match value {
+        "" | "(none)" => None,
+        _ => Some(value.to_string()),
+    };
 }
 
-/// Parse search output
-fn parse_search(output: &String) -> PackagesResponse {
+/// Replaces the versions and sizes from the transaction table with the exact
+/// values from the rpm database, for every package the transaction installed
+fn add_installed_details(res: &mut PackagesResponse) {
+    if res.packages.is_empty() {
+        return;
+    }
+    let names: Vec<&str> = res.packages.iter().map(|p| p.name.as_str()).collect();
+    let Ok(output) = run_command(
+        native_command("rpm")
+            .args(["-q", "--queryformat"])
+            .arg("%{NAME}\t%{ARCH}\t%{EPOCHNUM}\t%{VERSION}\t%{RELEASE}\t%{SIZE}\n")
+            .args(names),
+    ) else {
+        return;
+    };
+
+    let mut installed_byte_size: u64 = 0;
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 6 {
+            continue;
+        }
+        let Some(package) = res
+            .packages
+            .iter_mut()
+            .find(|p| p.name == fields[0] && p.arch == fields[1])
+        else {
+            continue;
+        };
+        package.version = Some(evr(fields[2], fields[3], fields[4]));
+        package.size_bytes = fields[5].parse().ok();
+        installed_byte_size += package.size_bytes.unwrap_or_default();
+    }
+    res.packages_size_bytes = Some(installed_byte_size);
+}
+
+/// Parse search output.
+/// dnf5 prints " ripgrep.x86_64: summary" under "Matched fields:" headers,
+/// dnf4 prints "ripgrep.x86_64 : summary" under "=== Name Matched: ripgrep ===" headers
+fn parse_search(output: &str) -> Result<PackagesResponse, PackagesError> {
     let mut res = response("search");
-    let mut lines = output.lines();
     if output.contains("Repositories loaded.") {
         res.messages.push("Updated repos".to_string());
-        while !lines.next().unwrap().contains("Repositories loaded.") {}
     }
     if output.contains("No matches found.") {
         res.messages.push("Nothing matched search".to_string());
-        return res;
+        return Ok(res);
     }
-    loop {
-        let line = lines.next().unwrap_or_default();
-        if line.is_empty() {
-            break;
-        }
-        if line.contains("Matched fields:") {
+    let headers = [
+        "Matched fields:",
+        "Updating and loading repositories:",
+        "Repositories loaded.",
+        "Last metadata expiration check:",
+        "=",
+    ];
+    for line in output.lines() {
+        if line.trim().is_empty() || headers.iter().any(|h| line.trim_start().starts_with(h)) {
             continue;
         }
-        let parts: Vec<&str> = line.split(":").collect();
-        let pack: Vec<&str> = parts[0].split(".").collect();
+        let Some((package, description)) = line.split_once(":") else {
+            return Err(SysadminError::parse(
+                line,
+                "expected a package and its summary",
+            ));
+        };
+        let (name, arch) = split_name_arch(package.trim(), line)?;
         res.packages.push(Package {
-            name: pack[0].trim_start().to_string(),
-            arch: pack[1].to_string(),
-            version: None,
-            old_version: None,
-            repository: None,
-            size: None,
-            download: None,
-            description: Some(parts[1].trim_start().to_string()),
-            url: None,
-            license: None,
-            installed: false,
+            name: name.to_string(),
+            arch: arch.to_string(),
+            description: Some(description.trim_start().to_string()),
+            ..Default::default()
         });
     }
     res.packages_length = Some(res.packages.len());
-    return res;
+    return Ok(res);
+}
+
+/// Splits "name.arch" into its parts, package names can contain "." too
+fn split_name_arch<'a>(package: &'a str, line: &str) -> Result<(&'a str, &'a str), PackagesError> {
+    return package
+        .rsplit_once(".")
+        .ok_or_else(|| SysadminError::parse(line, "expected a package name and architecture"));
 }
 
 /// Parse refresh output
-fn parse_refresh(_output: &String) -> PackagesResponse {
+fn parse_refresh(_output: &str) -> Result<PackagesResponse, PackagesError> {
     let mut res = response("refresh");
     res.messages.push("Updated repos".to_string());
-    return res;
+    return Ok(res);
 }
 
 /// Parse update output
-fn parse_update(output: &String) -> PackagesResponse {
-    let mut res = response("update");
-    if output.contains("Repositories loaded.") {
-        res.messages.push("Updated repos".to_string());
-    }
+fn parse_update(output: &str) -> Result<PackagesResponse, PackagesError> {
     if output.contains("Nothing to do.") {
+        let mut res = response("update");
+        if output.contains("Repositories loaded.") {
+            res.messages.push("Updated repos".to_string());
+        }
         res.messages.push("No updates available".to_string());
-        return res;
+        return Ok(res);
     }
-    let mut lines = output.lines();
-    let mut installed_byte_size: f64 = 0.0;
-    let mut uninstalled_byte_size: f64 = 0.0;
+    let mut res = parse_transaction("update", output)?;
+    res.uninstalled_packages_length = Some(res.uninstalled_packages.len());
+    return Ok(res);
+}
+
+/// Parse install output
+fn parse_install(output: &str) -> Result<PackagesResponse, PackagesError> {
+    if output.contains("Nothing to do.") {
+        let mut res = response("install");
+        if output.contains("Repositories loaded.") {
+            res.messages.push("Updated repos".to_string());
+        }
+        res.messages
+            .push("Package(s) already installed".to_string());
+        return Ok(res);
+    }
+    let mut res = parse_transaction("install", output)?;
+    res.uninstalled_packages_size_bytes = None;
+    return Ok(res);
+}
+
+/// Parse uninstall output
+fn parse_uninstall(output: &str) -> Result<PackagesResponse, PackagesError> {
+    if output.contains("Nothing to do.") {
+        let mut res = response("uninstall");
+        res.messages.push("No packages to uninstall".to_string());
+        return Ok(res);
+    }
+    let mut res = parse_transaction("uninstall", output)?;
+    res.messages.retain(|message| message != "Updated repos");
+    res.packages_size_bytes = None;
+    res.packages_length = None;
+    res.uninstalled_packages_length = Some(res.uninstalled_packages.len());
+    return Ok(res);
+}
+
+/// Parse autoremove output, the same as the one of uninstall
+fn parse_autoremove(output: &str) -> Result<PackagesResponse, PackagesError> {
+    return Ok(autoremoved(parse_uninstall(output)?));
+}
+
+/// Parse undo output
+fn parse_undo(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_transaction("undo", output)?;
+    if res.packages.is_empty() && res.uninstalled_packages.is_empty() {
+        res.messages.push("Nothing to undo".to_string());
+    }
+    res.uninstalled_packages_length = Some(res.uninstalled_packages.len());
+    return Ok(res);
+}
 
-    // Loop to parse "Removing" section of packages
-    while !lines.next().unwrap().contains("Removing:") {}
-    loop {
-        let line = lines.next().unwrap_or_default();
-        if line.contains("Removing dependent packages:") {
+/// Parse the output of "dnf history info" for one or more transactions, e.g.
+///
+/// Transaction ID : 3
+/// Begin time     : Mon 01 Apr 2024 10:00:00 AM UTC
+/// User           : root <root>
+/// Command Line   : install ripgrep
+/// Packages Altered:
+///     Upgrade  curl-8.6.0-4.fc39.x86_64 @updates
+///     Upgraded curl-8.6.0-3.fc39.x86_64 @@System
+///
+/// dnf5 prints "Start time", "User : 0 root", "Description" and a table of
+/// "Action Package Reason Repository" rows with "Replaced" for the old versions
+fn parse_history(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("history");
+    let mut altered = false;
+    for line in output.lines() {
+        if let Some((key, value)) = line.split_once(" : ").or_else(|| line.split_once(" :")) {
+            let value = value.trim();
+            match key.trim() {
+                "Transaction ID" => {
+                    res.transactions.push(Transaction::new(value.to_string()));
+                    altered = false;
+                }
+                "Begin time" => {
+                    if let Some(transaction) = res.transactions.last_mut() {
+                        transaction.timestamp = history_time(value);
+                    }
+                }
+                "Start time" => {
+                    if let Some(transaction) = res.transactions.last_mut() {
+                        transaction.timestamp = Some(
+                            value
+                                .split_whitespace()
+                                .take(2)
+                                .collect::<Vec<_>>()
+                                .join(" "),
+                        );
+                    }
+                }
+                // e.g. "root <root>" on dnf4, "0 root" on dnf5
+                "User" => {
+                    if let Some(transaction) = res.transactions.last_mut() {
+                        transaction.user = match value.split_once('<') {
+                            Some((_, login)) => Some(login.trim_end_matches('>').to_string()),
+                            None => value.split_whitespace().last().map(|user| user.to_string()),
+                        };
+                    }
+                }
+                "Command Line" | "Description" => {
+                    if let Some(transaction) = res.transactions.last_mut() {
+                        transaction.command = optional(value);
+                    }
+                }
+                _ => {}
+            }
             continue;
         }
-        if !line.starts_with(" ") {
-            break;
+        if line.trim_end().eq_ignore_ascii_case("Packages Altered:") {
+            altered = true;
+            continue;
         }
+        if !altered || !line.starts_with(' ') {
+            altered = altered && line.trim().is_empty();
+            continue;
+        }
+        let Some(transaction) = res.transactions.last_mut() else {
+            continue;
+        };
         let parts: Vec<&str> = line.split_whitespace().collect();
-        uninstalled_byte_size += get_byte_size(parts[4], parts[5]);
-        res.uninstalled_packages.push(Package {
-            name: parts[0].to_string(),
-            arch: parts[1].to_string(),
-            version: Some(parts[2].to_string()),
-            old_version: None,
-            repository: Some(parts[3].to_string()),
-            size: Some(format!("{} {}", parts[4], parts[5])),
-            download: None,
-            description: None,
-            url: None,
-            license: None,
+        if parts.len() < 2 {
+            continue;
+        }
+        let (action, installing) = match parts[0] {
+            "Install" | "Dep-Install" => ("install", true),
+            "Upgrade" => ("upgrade", true),
+            "Downgrade" => ("downgrade", true),
+            "Reinstall" => ("reinstall", true),
+            "Erase" | "Removed" | "Remove" => ("remove", false),
+            // The old versions of upgrades, downgrades and reinstalls
+            "Upgraded" | "Downgraded" | "Reinstalled" | "Replaced" | "Obsoleted" => ("", false),
+            _ => continue,
+        };
+        let (name, version, arch) = split_nevra(parts[1], line)?;
+        if action.is_empty() {
+            let replaced = transaction.packages.iter_mut().find(|package| {
+                package.name == name && package.installed && package.old_version.is_none()
+            });
+            if let Some(package) = replaced {
+                if package.version.as_deref() != Some(version.as_str()) {
+                    package.old_version = Some(version);
+                }
+                continue;
+            }
+            // Packages obsoleted by a package with another name are removed
+            transaction.add_action("remove");
+        } else {
+            transaction.add_action(action);
+        }
+        let repository = parts
+            .get(2)
+            .filter(|repository| repository.starts_with('@'))
+            .or(parts.get(3))
+            .map(|repository| repository.trim_start_matches('@').to_string());
+        transaction.packages.push(Package {
+            name: name.to_string(),
+            arch: arch.to_string(),
+            version: Some(version),
+            repository: repository.filter(|repository| installing && repository != "System"),
+            installed: installing,
+            ..Default::default()
+        });
+    }
+    if res.transactions.is_empty() {
+        res.messages
+            .push("No transactions in the history".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parse versionlock list output, dnf4 prints a pattern like "curl-0:8.6.0-4.fc39.*" for every lock,
+/// dnf5 prints "Package name: curl" followed by conditions like "evr = 0:8.6.0-4.fc40"
+fn parse_holds(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("holds");
+    for line in output.lines() {
+        let line = line.trim();
+        let (name, version) = if let Some(name) = line.strip_prefix("Package name: ") {
+            (name, None)
+        } else if let Some(version) = line.strip_prefix("evr = ") {
+            if let Some(package) = res.packages.last_mut() {
+                let (epoch, version) = version.split_once(':').unwrap_or(("", version));
+                let (version, release) = version.rsplit_once('-').unwrap_or((version, ""));
+                package.version = Some(evr(epoch, version, release));
+            }
+            continue;
+        } else if line.ends_with(".*") && !line.starts_with('!') {
+            // The arch of the pattern is always "*"
+            let (name, version, _) = split_nevra(line, line)?;
+            (name, Some(version))
+        } else {
+            continue;
+        };
+        res.packages.push(Package {
+            name: name.to_string(),
+            version,
+            // Locks don't tell whether the package is installed
             installed: false,
-        })
+            ..Default::default()
+        });
     }
+    if res.packages.is_empty() {
+        res.messages.push("No packages are held".to_string());
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
 
-    // Loop to parse "Upgrading" section of packages
-    lines = output.lines();
-    while !lines.next().unwrap().contains("Upgrading:") {}
-    loop {
-        let line = lines.next().unwrap_or_default();
-        if line.contains("Installing:") {
+/// Parse the output of "dnf updateinfo list" followed by the one of "dnf updateinfo list --with-cve".
+/// dnf4 prints e.g. "FEDORA-2024-1a2b3c4d5e Moderate/Sec. curl-8.6.0-4.fc39.x86_64", dnf5 prints
+/// "FEDORA-2024-1a2b3c4d5e security Moderate curl-8.6.0-4.fc40.x86_64 2024-04-01 10:00:00".
+/// The "--with-cve" rows have a CVE in place of the advisory id
+fn parse_advisories(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("advisories");
+    let mut cves: Vec<(String, Package)> = vec![];
+    for line in output.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let (kind, severity, package, issued) = match parts[..] {
+            // dnf4, e.g. "Moderate/Sec." or "bugfix"
+            [_, kind, package] => match kind.split_once("Sec.") {
+                Some((severity, _)) => (
+                    "security",
+                    optional(severity.trim_end_matches('/')),
+                    package,
+                    None,
+                ),
+                None => (kind, None, package, None),
+            },
+            // dnf5, without a severity for advisories that have none
+            [_, kind, package, date, time] => {
+                (kind, None, package, Some(format!("{} {}", date, time)))
+            }
+            [_, kind, severity, package, date, time] => (
+                kind,
+                optional(severity),
+                package,
+                Some(format!("{} {}", date, time)),
+            ),
+            _ => continue,
+        };
+        // Header and metadata lines also have 3 to 6 words
+        if !package.contains('-') || !package.contains('.') || parts[0] == "Name" {
             continue;
         }
-        if !line.starts_with(" ") {
-            break;
+        let (name, version, arch) = split_nevra(package, line)?;
+        let package = Package {
+            name: name.to_string(),
+            arch: arch.to_string(),
+            version: Some(version),
+            ..Default::default()
+        };
+        if parts[0].starts_with("CVE-") {
+            cves.push((parts[0].to_string(), package));
+            continue;
         }
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts[0] == "replacing" {
-            let i = res.packages.len() - 1;
-            res.packages[i].old_version = Some(parts[3].to_string());
-            uninstalled_byte_size += get_byte_size(parts[5], parts[6]);
-        } else {
-            installed_byte_size += get_byte_size(parts[4], parts[5]);
-            res.packages.push(Package {
-                name: parts[0].to_string(),
-                arch: parts[1].to_string(),
-                version: Some(parts[2].to_string()),
-                old_version: None,
-                repository: Some(parts[3].to_string()),
-                size: Some(format!("{} {}", parts[4], parts[5])),
-                download: None,
-                description: None,
-                url: None,
-                license: None,
-                installed: true,
-            })
-        }
-    }
-
-    let uninstalled_size = simplify_byte_size(uninstalled_byte_size.to_string().as_str());
-    let installed_size = simplify_byte_size(installed_byte_size.to_string().as_str());
-    res.uninstalled_packages_size = Some(uninstalled_size);
-    res.packages_size = Some(installed_size);
-    res.packages_length = Some(res.packages.len());
-    res.uninstalled_packages_length = Some(res.uninstalled_packages.len());
-    return res;
+        // An advisory that updates several packages has a row for each of them
+        if res.advisories.last().map(|advisory| advisory.id.as_str()) != Some(parts[0]) {
+            res.advisories.push(Advisory {
+                id: parts[0].to_string(),
+                kind: Some(kind.to_string()),
+                severity: severity.filter(|severity| severity != "None"),
+                title: None,
+                issued,
+                cves: vec![],
+                packages: vec![],
+            });
+        }
+        res.advisories.last_mut().unwrap().packages.push(package);
+    }
+    for (cve, package) in cves {
+        for advisory in res.advisories.iter_mut() {
+            let fixes = advisory
+                .packages
+                .iter()
+                .any(|fixed| fixed.name == package.name && fixed.version == package.version);
+            if fixes && !advisory.cves.contains(&cve) {
+                advisory.cves.push(cve.clone());
+            }
+        }
+    }
+    return Ok(res);
 }
 
-/// Parse install output
-fn parse_install(output: &String) -> PackagesResponse {
-    let mut res = response("install");
+/// Splits a package like "curl-0:8.6.0-4.fc40.x86_64" into its name, version and arch
+fn split_nevra<'a>(
+    package: &'a str,
+    line: &str,
+) -> Result<(&'a str, String, &'a str), PackagesError> {
+    let (nevr, arch) = package
+        .rsplit_once('.')
+        .ok_or_else(|| SysadminError::parse(line, "expected an arch"))?;
+    let mut parts = nevr.rsplitn(3, '-');
+    let (Some(release), Some(version), Some(name)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(SysadminError::parse(line, "expected name-version-release"));
+    };
+    let (epoch, version) = version.split_once(':').unwrap_or(("", version));
+    return Ok((name, evr(epoch, version, release), arch));
+}
+
+/// Converts a time dnf4 prints in the format of the locale, e.g. "Mon 01 Apr 2024 10:00:00 AM UTC"
+/// or "Mon Apr  1 10:00:00 2024", to "YYYY-MM-DD HH:MM:SS"
+fn history_time(time: &str) -> Option<String> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let (mut year, mut month, mut day, mut clock) = (None, None, None, None);
+    let mut afternoon = false;
+    for word in time.split_whitespace() {
+        if let Some(index) = MONTHS.iter().position(|m| word.starts_with(m)) {
+            month = Some(index + 1);
+        } else if word.contains(':') {
+            clock = Some(word);
+        } else if word == "PM" {
+            afternoon = true;
+        } else if let Ok(number) = word.parse::<u32>() {
+            match word.len() {
+                4 => year = Some(number),
+                1 | 2 => day = Some(number),
+                _ => {}
+            }
+        }
+    }
+    let clock: Vec<u32> = clock?
+        .split(':')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+    let [hour, minute, second] = clock[..] else {
+        return None;
+    };
+    // "12:00:00 AM" is midnight and "12:00:00 PM" is noon
+    let hour = match (time.contains(" AM") || afternoon, afternoon) {
+        (true, true) => hour % 12 + 12,
+        (true, false) => hour % 12,
+        (false, _) => hour,
+    };
+    return Some(format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year?, month?, day?, hour, minute, second
+    ));
+}
+
+/// Parses the transaction table dnf prints before applying a transaction.
+/// dnf5 shows the installed size of each package, dnf4 the download size, e.g.
+///
+/// Upgrading:
+///  curl         x86_64  8.6.0-4.fc40  updates  789.2 KiB
+///    replacing  curl    x86_64        8.6.0-3.fc40  updates  780.1 KiB
+/// Removing:
+///  oldpkg       noarch  1.0-1.fc40    @System  10.0 KiB
+///
+/// dnf4 wraps the rows of long names onto the next line, e.g.
+///
+///  python3-very-long-package-name
+///               noarch  1.0-1.fc39    updates  45 k
+fn parse_transaction(
+    subcommand: &'static str,
+    output: &str,
+) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response(subcommand);
     if output.contains("Repositories loaded.") {
         res.messages.push("Updated repos".to_string());
     }
-    if output.contains("Nothing to do.") {
-        res.messages
-            .push("Package(s) already installed".to_string());
-        return res;
-    }
-    let mut lines = output.lines();
-    while !lines.next().unwrap().contains("Installing:") {}
+    let mut installed_byte_size: u64 = 0;
+    let mut uninstalled_byte_size: u64 = 0;
 
-    let mut byte_size: f64 = 0.0;
-    loop {
-        let line = lines.next().unwrap();
-        if line.is_empty() {
+    let mut section = "";
+    let mut wrapped: Vec<&str> = vec![];
+    for line in output.lines() {
+        if !line.starts_with(" ") && !wrapped.is_empty() {
+            // The transaction already ran, a row that is short rather than wrapped mustn't fail it
+            res.messages.push(format!(
+                "Skipped the transaction row \"{}\"",
+                wrapped.join(" ")
+            ));
+            wrapped.clear();
+        }
+        if line.starts_with("Transaction Summary") {
             break;
         }
-        if line.contains("Installing dependencies:") {
+        if !line.starts_with(" ") {
+            section = line;
             continue;
         }
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        byte_size += get_byte_size(parts[4], parts[5]);
-        res.packages.push(Package {
+        let installing = ["Installing", "Upgrading", "Downgrading", "Reinstalling"]
+            .iter()
+            .any(|header| section.starts_with(header));
+        let removing = section.starts_with("Removing");
+        if !installing && !removing {
+            continue;
+        }
+
+        let mut parts = std::mem::take(&mut wrapped);
+        parts.extend(line.split_whitespace());
+        let columns = if parts.first() == Some(&"replacing") {
+            3
+        } else {
+            6
+        };
+        if parts.len() < columns {
+            wrapped = parts;
+            continue;
+        }
+        if parts.first() == Some(&"replacing") {
+            let Some(package) = res.packages.last_mut() else {
+                return Err(SysadminError::parse(line, "nothing to replace"));
+            };
+            match parts.len() {
+                // dnf4: "replacing  curl.x86_64 8.6.0-3.fc39"
+                3 => package.old_version = Some(parts[2].to_string()),
+                // dnf5: "replacing curl x86_64 8.6.0-3.fc40 updates 780.1 KiB"
+                7 => {
+                    package.old_version = Some(parts[3].to_string());
+                    uninstalled_byte_size += get_byte_size(parts[5], parts[6]).unwrap_or_default();
+                }
+                _ => return Err(SysadminError::parse(line, "expected 3 or 7 columns")),
+            }
+            continue;
+        }
+        let package = Package {
             name: parts[0].to_string(),
             arch: parts[1].to_string(),
             version: Some(parts[2].to_string()),
-            old_version: None,
             repository: Some(parts[3].to_string()),
-            size: Some(format!("{} {}", parts[4], parts[5])),
-            download: None,
-            description: None,
-            url: None,
-            license: None,
-            installed: true,
-        });
+            installed: installing,
+            size_bytes: get_byte_size(parts[4], parts[5]),
+            ..Default::default()
+        };
+        if installing {
+            installed_byte_size += package.size_bytes.unwrap_or_default();
+            res.packages.push(package);
+        } else {
+            uninstalled_byte_size += package.size_bytes.unwrap_or_default();
+            res.uninstalled_packages.push(package);
+        }
     }
-    res.packages_size = Some(simplify_byte_size(byte_size.to_string().as_str()));
+
+    res.uninstalled_packages_size_bytes = Some(uninstalled_byte_size);
+    res.packages_size_bytes = Some(installed_byte_size);
     res.packages_length = Some(res.packages.len());
-    return res;
+    return Ok(res);
 }
 
-/// Parse uninstall output
-fn parse_uninstall(output: &String) -> PackagesResponse {
-    let mut res = response("uninstall");
-    if output.contains("Nothing to do.") {
-        res.messages.push("No packages to uninstall".to_string());
-        return res;
+/// Parses a line dnf prints while it runs.
+/// dnf5 prints e.g. "[1/3] curl-0:8.6.0-4.fc40.x86_64  100% | 1.0 MiB/s | 301.7 KiB | 00m00s" or "[5/8] Upgrading curl-0:8.6.0-4.fc40.x86_64 100% | ...",
+/// dnf4 prints e.g. "(1/3): curl-8.6.0-4.fc39.x86_64.rpm  301 kB/s | 301 kB  00:01" or "  Upgrading        : curl-8.6.0-4.fc39.x86_64   1/3"
+pub(super) fn parse_progress(line: &str) -> Option<ProgressEvent> {
+    let trimmed = line.trim();
+    if trimmed.starts_with("Updating and loading repositories") || trimmed.contains("metadata") {
+        return Some(ProgressEvent::new(Phase::Refreshing, line));
     }
-    let mut lines = output.lines();
-    while !lines.next().unwrap().contains("Removing:") {}
-
-    let mut byte_size: f64 = 0.0;
-    loop {
-        let line = lines.next().unwrap();
-        if line.is_empty() {
-            break;
-        }
-        if line.contains("Removing unused dependencies:") {
-            continue;
+    let parts: Vec<&str> = trimmed.split_whitespace().collect();
+    let counter = parts.first()?.trim_end_matches(':');
+    if let Some(percent) = counter_percent(counter) {
+        let (phase, package) = match parts.get(1..3) {
+            Some(["Verify", _]) => (Phase::Verifying, None),
+            Some(["Prepare", _]) => (Phase::Verifying, None),
+            Some([action, package]) => match transaction_phase(action) {
+                Some(phase) => (phase, Some(package)),
+                None => (Phase::Downloading, parts.get(1)),
+            },
+            _ => (Phase::Downloading, parts.get(1)),
+        };
+        let mut event = ProgressEvent::new(phase, line);
+        event.package = package.map(|package| package.trim_end_matches(".rpm").to_string());
+        event.percent = Some(percent);
+        if phase == Phase::Downloading {
+            // The size is after the speed, e.g. "| 301.7 KiB |" on dnf5 or "| 301 kB  00:01" on dnf4
+            let column = match parts[0].ends_with(':') {
+                true => 1,
+                false => 2,
+            };
+            event.download_bytes = line.split('|').nth(column).and_then(|size| {
+                let words: Vec<&str> = size.split_whitespace().collect();
+                return get_byte_size(words.first()?, words.get(1)?);
+            });
         }
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        byte_size += get_byte_size(parts[4], parts[5]);
-        res.uninstalled_packages.push(Package {
-            name: parts[0].to_string(),
-            arch: parts[1].to_string(),
-            version: Some(parts[2].to_string()),
-            old_version: None,
-            repository: Some(parts[3].to_string()),
-            size: Some(format!("{} {}", parts[4], parts[5])),
-            download: None,
-            description: None,
-            url: None,
-            license: None,
-            installed: true,
-        });
+        return Some(event);
     }
-    res.uninstalled_packages_size = Some(simplify_byte_size(byte_size.to_string().as_str()));
-    res.uninstalled_packages_length = Some(res.uninstalled_packages.len());
-    return res;
+    // dnf4 transaction lines, "Action : package counter"
+    let (action, rest) = trimmed.split_once(" : ")?;
+    let phase = match action.trim() {
+        "Verifying" => Phase::Verifying,
+        "Running scriptlet" => Phase::Configuring,
+        action => transaction_phase(action)?,
+    };
+    let words: Vec<&str> = rest.split_whitespace().collect();
+    let mut event = ProgressEvent::new(phase, line);
+    event.package = words.first().map(|package| package.to_string());
+    event.percent = words.last().and_then(|counter| counter_percent(counter));
+    return Some(event);
+}
+
+fn transaction_phase(action: &str) -> Option<Phase> {
+    return match action {
+        "Installing" | "Reinstalling" => Some(Phase::Installing),
+        "Upgrading" | "Downgrading" | "Replacing" => Some(Phase::Updating),
+        "Removing" | "Erasing" | "Cleanup" | "Obsoleting" => Some(Phase::Removing),
+        _ => None,
+    };
 }
@@ -0,0 +1,401 @@
+use super::*;
+
+/// Flatpak apps and runtimes, either of every installation or only of the user installation
+pub struct Flatpak {
+    user: bool,
+}
+
+pub static FLATPAK: Flatpak = Flatpak { user: false };
+
+pub static FLATPAK_USER: Flatpak = Flatpak { user: true };
+
+/// Columns of "flatpak list", printed tab separated
+const LIST_COLUMNS: &str = "application,arch,version,branch,origin,installation,size,description";
+
+/// Columns of "flatpak remote-ls", the same as LIST_COLUMNS with the download size instead of the installation
+const REMOTE_COLUMNS: &str =
+    "application,arch,version,branch,origin,download-size,installed-size,description";
+
+impl Flatpak {
+    /// Creates a flatpak command for a subcommand, limited to the user installation if wanted
+    fn command(&self, subcommand: &str) -> Command {
+        let mut command = native_command("flatpak");
+        command.arg(subcommand);
+        if self.user {
+            command.arg("--user");
+        }
+        return command;
+    }
+}
+
+impl PackageBackend for Flatpak {
+    fn manager(&self) -> &'static str {
+        return "flatpak";
+    }
+
+    fn list(
+        &self,
+        installed: bool,
+        available: bool,
+        extras: bool,
+        packages: &[String],
+    ) -> Result<PackagesResponse, PackagesError> {
+        if extras {
+            return Err(unsupported(self.manager(), "list extra packages"));
+        }
+        let mut res = response("list");
+        // "flatpak list" only lists installed refs, the available ones come from the remotes
+        if installed || !available {
+            let output = run_command(
+                self.command("list")
+                    .arg(format!("--columns={}", LIST_COLUMNS))
+                    .args(packages),
+            )?;
+            let mut installed = parse_output(output, parse_list)?;
+            res.packages.append(&mut installed.packages);
+            res.out.push_str(&installed.out);
+        }
+        if available {
+            let output = run_command(
+                self.command("remote-ls")
+                    .arg(format!("--columns={}", REMOTE_COLUMNS))
+                    .args(packages),
+            )?;
+            let mut available = parse_output(output, parse_available)?;
+            res.packages.append(&mut available.packages);
+            res.out.push_str(&available.out);
+        }
+        res.packages_length = Some(res.packages.len());
+        return Ok(res);
+    }
+
+    fn info(&self, packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        // "flatpak info" only takes a single ref
+        let mut output = String::new();
+        for package in packages {
+            output.push_str(&run_command(self.command("info").arg(package))?);
+        }
+        return parse_output(output, parse_info);
+    }
+
+    fn search(&self, search_terms: &[String]) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(
+            self.command("search")
+                .arg("--columns=application,version,branch,remotes,description")
+                .args(search_terms),
+        )?;
+        return parse_output(output, parse_search);
+    }
+
+    fn refresh(&self) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(
+            self.command("update")
+                .args(["--appstream", "--noninteractive"]),
+        )?;
+        return parse_output(output, parse_refresh);
+    }
+
+    fn update(&self, fake: bool, dry_run: bool) -> Result<PackagesResponse, PackagesError> {
+        if dry_run {
+            let output = run_command(
+                self.command("remote-ls")
+                    .args(["--updates", &format!("--columns={}", REMOTE_COLUMNS)]),
+            )?;
+            return parse_output(output, parse_pending_update);
+        }
+        let mut command = self.command("update");
+        command.args(["-y", "--noninteractive"]);
+        if fake {
+            command.arg("--no-deploy");
+        }
+        let output = run_command(&mut command)?;
+        return parse_output(output, parse_update);
+    }
+
+    fn install(
+        &self,
+        packages: &[String],
+        dry_run: bool,
+    ) -> Result<PackagesResponse, PackagesError> {
+        if dry_run {
+            return Err(unsupported(self.manager(), "install packages as a dry run"));
+        }
+        let output = run_command(
+            self.command("install")
+                .args(["-y", "--noninteractive"])
+                .args(packages),
+        )?;
+        return parse_output(output, parse_install);
+    }
+
+    fn uninstall(
+        &self,
+        packages: &[String],
+        dry_run: bool,
+    ) -> Result<PackagesResponse, PackagesError> {
+        if dry_run {
+            return Err(unsupported(
+                self.manager(),
+                "uninstall packages as a dry run",
+            ));
+        }
+        let output = run_command(
+            self.command("uninstall")
+                .args(["-y", "--noninteractive"])
+                .args(packages),
+        )?;
+        return parse_output(output, parse_uninstall);
+    }
+}
+
+/// Gets the parser for the output of a subcommand, used by the fixture tests
+#[cfg(test)]
+pub(super) fn parser(subcommand: &str) -> Option<Parser> {
+    return match subcommand {
+        "list" => Some(parse_list),
+        "available" => Some(parse_available),
+        "info" => Some(parse_info),
+        "search" => Some(parse_search),
+        "refresh" => Some(parse_refresh),
+        "update" => Some(parse_update),
+        "pending_update" => Some(parse_pending_update),
+        "install" => Some(parse_install),
+        "uninstall" => Some(parse_uninstall),
+        _ => None,
+    };
+}
+
+/// Parse list output with the LIST_COLUMNS
+fn parse_list(output: &str) -> Result<PackagesResponse, PackagesError> {
+    return parse_columns(output, true);
+}
+
+/// Parse remote-ls output with the REMOTE_COLUMNS
+fn parse_available(output: &str) -> Result<PackagesResponse, PackagesError> {
+    return parse_columns(output, false);
+}
+
+fn parse_columns(output: &str, installed: bool) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("list");
+    for line in output.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        // e.g. "org.mozilla.firefox	x86_64	125.0.2	stable	flathub	system	254.5 MB	Fast, Private & Safe Web Browser"
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 8 {
+            return Err(SysadminError::parse(line, "expected 8 columns"));
+        }
+        let (installation, download_bytes) = match installed {
+            true => (optional(fields[5]), None),
+            false => (None, parse_byte_size(fields[5])),
+        };
+        res.packages.push(Package {
+            name: fields[0].to_string(),
+            arch: fields[1].to_string(),
+            version: version(fields[2], fields[3]),
+            repository: optional(fields[4]),
+            description: optional(fields[7]),
+            installed,
+            size_bytes: parse_byte_size(fields[6]),
+            download_bytes,
+            installation,
+            ..Default::default()
+        });
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Runtimes often have no version, their branch (e.g. "23.08") is used instead
+fn version(version: &str, branch: &str) -> Option<String> {
+    return optional(version).or(optional(branch));
+}
+
+fn optional(value: &str) -> Option<String> {
+    return match value.trim() {
+        "" => None,
+        value => Some(value.to_string()),
+    };
+}
+
+/// Parse info output.
+/// Every package starts with a "Name - summary" line, followed by right aligned
+/// "Key: Value" lines starting with its ID, e.g. "          ID: org.mozilla.firefox"
+fn parse_info(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("info");
+    let mut summary: Option<String> = None;
+    for line in output.lines() {
+        let line = line.trim();
+        let Some((field, value)) = line.split_once(": ") else {
+            // The "Name - summary" line before every package
+            if let Some((_, description)) = line.split_once(" - ") {
+                summary = Some(description.to_string());
+            }
+            continue;
+        };
+        if field == "ID" {
+            res.packages.push(Package {
+                name: value.to_string(),
+                description: summary.take(),
+                installed: true,
+                ..Default::default()
+            });
+            continue;
+        }
+        let Some(package) = res.packages.last_mut() else {
+            continue;
+        };
+        let value = value.trim().to_string();
+        match field {
+            "Arch" => package.arch = value,
+            "Version" => package.version = Some(value),
+            "Branch" if package.version.is_none() => package.version = Some(value),
+            "License" => package.license = Some(value),
+            "Origin" => package.repository = Some(value),
+            "Installation" => package.installation = Some(value),
+            "Installed" => package.size_bytes = parse_byte_size(&value),
+            _ => {}
+        }
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Parse search output
+fn parse_search(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("search");
+    for line in output.lines() {
+        // e.g. "org.gimp.GIMP	2.10.38	stable	flathub	High-end image creation and manipulation"
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 5 {
+            continue;
+        }
+        res.packages.push(Package {
+            name: fields[0].to_string(),
+            version: version(fields[1], fields[2]),
+            repository: optional(fields[3]),
+            description: optional(fields[4]),
+            ..Default::default()
+        });
+    }
+    if res.packages.is_empty() {
+        res.messages.push("Nothing matched search".to_string());
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Parse refresh output
+fn parse_refresh(_output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("refresh");
+    res.messages.push("Updated repos".to_string());
+    return Ok(res);
+}
+
+/// Parse the output of "remote-ls --updates" for a dry run of an update
+fn parse_pending_update(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_columns(output, false)?;
+    res.subcommand = "update";
+    // Updates are only listed for installed refs
+    for package in res.packages.iter_mut() {
+        package.installed = true;
+    }
+    if res.packages.is_empty() {
+        res.messages.push("No updates available".to_string());
+    } else {
+        res.packages_size_bytes = res.packages.iter().map(|p| p.download_bytes).sum();
+    }
+    return Ok(res);
+}
+
+/// Parse update output
+fn parse_update(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_transaction("update", output);
+    if res.packages.is_empty() && res.uninstalled_packages.is_empty() {
+        res.messages.push("No updates available".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parse install output
+fn parse_install(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_transaction("install", output);
+    if res.packages.is_empty() {
+        res.messages
+            .push("Package(s) already installed".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parse uninstall output
+fn parse_uninstall(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_transaction("uninstall", output);
+    if res.uninstalled_packages.is_empty() {
+        res.messages.push("No packages to uninstall".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parses the lines a noninteractive transaction prints for every ref, e.g.
+/// "Updating app/org.mozilla.firefox/x86_64/stable" or "Uninstalling runtime/org.gtk.Gtk3theme.Adwaita-dark/x86_64/3.22"
+fn parse_transaction(subcommand: &'static str, output: &str) -> PackagesResponse {
+    let mut res = response(subcommand);
+    for line in output.lines() {
+        let Some((action, flatpak_ref)) = line.trim().split_once(' ') else {
+            continue;
+        };
+        let installing = match action {
+            "Installing" | "Updating" => true,
+            "Uninstalling" => false,
+            _ => continue,
+        };
+        // Refs are "kind/name/arch/branch"
+        let parts: Vec<&str> = flatpak_ref.trim().split('/').collect();
+        if parts.len() != 4 {
+            continue;
+        }
+        let package = Package {
+            name: parts[1].to_string(),
+            arch: parts[2].to_string(),
+            version: Some(parts[3].to_string()),
+            installed: installing,
+            ..Default::default()
+        };
+        if installing {
+            res.packages.push(package);
+        } else {
+            res.uninstalled_packages.push(package);
+        }
+    }
+    if !res.packages.is_empty() {
+        res.packages_length = Some(res.packages.len());
+    }
+    if !res.uninstalled_packages.is_empty() {
+        res.uninstalled_packages_length = Some(res.uninstalled_packages.len());
+    }
+    return res;
+}
+
+/// Parses a line flatpak prints while it runs, e.g. "Installing app/org.gnome.Calculator/x86_64/stable"
+/// or a redrawn progress bar like "Installing 1/2… ████████▍            45%  3.4 MB/s  00:10"
+pub(super) fn parse_progress(line: &str) -> Option<ProgressEvent> {
+    let (action, rest) = line.trim().split_once(' ')?;
+    let phase = match action {
+        "Installing" => Phase::Installing,
+        "Updating" => Phase::Updating,
+        "Uninstalling" => Phase::Removing,
+        "Looking" => Phase::Refreshing,
+        _ => return None,
+    };
+    let mut event = ProgressEvent::new(phase, line);
+    let words: Vec<&str> = rest.split_whitespace().collect();
+    let first = words.first().copied().unwrap_or_default();
+    let parts: Vec<&str> = first.split('/').collect();
+    if parts.len() == 4 {
+        event.package = Some(parts[1].to_string());
+    } else {
+        event.percent = shown_percent(rest).or(counter_percent(first.trim_end_matches('…')));
+    }
+    return Some(event);
+}
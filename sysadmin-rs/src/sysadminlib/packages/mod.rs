@@ -1,311 +1,1300 @@
 use super::*;
+use crate::error::{ErrorKind, SysadminError};
+use crate::os::OsRelease;
 use crate::Exit;
+use progress::{
+    counter_percent, progress_sink, read_lines, shown_percent, Phase, ProgressEvent,
+    ProgressParser, ProgressSink,
+};
 use serde::{Deserialize, Serialize};
-use std::process::Command;
+use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
 use std::str;
+use std::thread;
 
 mod apk;
 mod apt;
 mod dnf;
+mod flatpak;
+mod pacman;
+pub mod progress;
+mod repos;
+mod rpm;
+mod snap;
 mod zypper;
 
-pub struct PackageManager {
-    manager: &'static str,
-    list: ListArgs,
-    info: InfoArgs,
-    search: SearchArgs,
-    refresh_data: RefreshArgs,
-    update: UpdateArgs,
-    install: InstallArgs,
-    uninstall: UninstallArgs,
-}
+#[cfg(test)]
+mod tests;
+
+/// Interface for a native package manager or an app store like Flatpak or Snap.
+/// Every operation runs the package manager and parses its output into a PackagesResponse
+pub trait PackageBackend: Sync {
+    /// Name of the package manager, used as the "real_command" of an Exit
+    fn manager(&self) -> &'static str;
+
+    fn list(
+        &self,
+        installed: bool,
+        available: bool,
+        extras: bool,
+        packages: &[String],
+    ) -> Result<PackagesResponse, PackagesError>;
+
+    fn info(&self, packages: &[String]) -> Result<PackagesResponse, PackagesError>;
+
+    fn search(&self, search_terms: &[String]) -> Result<PackagesResponse, PackagesError>;
+
+    fn refresh(&self) -> Result<PackagesResponse, PackagesError>;
+
+    /// Updates every package. With dry_run the transaction is only resolved and returned, nothing is changed
+    fn update(&self, fake: bool, dry_run: bool) -> Result<PackagesResponse, PackagesError>;
+
+    fn install(
+        &self,
+        packages: &[String],
+        dry_run: bool,
+    ) -> Result<PackagesResponse, PackagesError>;
+
+    fn uninstall(
+        &self,
+        packages: &[String],
+        dry_run: bool,
+    ) -> Result<PackagesResponse, PackagesError>;
+
+    /// Gets the newest transactions of the package manager, newest first
+    fn history(&self, _limit: usize) -> Result<PackagesResponse, PackagesError> {
+        return Err(unsupported(self.manager(), "show its transaction history"));
+    }
+
+    /// Reverts a transaction of the history
+    fn undo(&self, _id: &str) -> Result<PackagesResponse, PackagesError> {
+        return Err(unsupported(self.manager(), "undo transactions"));
+    }
+
+    /// Gets the repositories in the configuration of the package manager
+    fn repos(&self) -> Result<PackagesResponse, PackagesError> {
+        return Err(unsupported(self.manager(), "list repositories"));
+    }
+
+    fn enable_repo(&self, _id: &str, _enabled: bool) -> Result<PackagesResponse, PackagesError> {
+        return Err(unsupported(
+            self.manager(),
+            "enable or disable repositories",
+        ));
+    }
+
+    /// Adds a repository to the configuration, the id of the repository is empty if none was given
+    fn add_repo(&self, _repo: &Repository) -> Result<PackagesResponse, PackagesError> {
+        return Err(unsupported(self.manager(), "add repositories"));
+    }
+
+    fn remove_repo(&self, _id: &str) -> Result<PackagesResponse, PackagesError> {
+        return Err(unsupported(self.manager(), "remove repositories"));
+    }
+
+    /// Holds packages at their installed version and returns the held packages
+    fn hold(&self, _packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        return Err(unsupported(self.manager(), "hold packages"));
+    }
+
+    /// Releases held packages and returns the packages that are still held
+    fn unhold(&self, _packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        return Err(unsupported(self.manager(), "release held packages"));
+    }
+
+    /// Gets the held packages, their version is the one they are held at, or None if the whole package is held
+    fn holds(&self) -> Result<PackagesResponse, PackagesError> {
+        return Err(unsupported(self.manager(), "list held packages"));
+    }
 
-pub struct ListArgs {
-    command: &'static [&'static str],
-    installed: &'static str,
-    available: &'static str,
-    extras: &'static str,
-    parser: fn(&String) -> PackagesResponse,
+    /// Gets the pending advisories with the updates that apply them
+    fn advisories(&self) -> Result<PackagesResponse, PackagesError> {
+        return Err(unsupported(self.manager(), "list advisories"));
+    }
+
+    /// Applies only some advisories, the pending advisories that match the filter are given
+    fn update_advisories(
+        &self,
+        _filter: &AdvisoryFilter,
+        _advisories: &[Advisory],
+        _dry_run: bool,
+    ) -> Result<PackagesResponse, PackagesError> {
+        return Err(unsupported(self.manager(), "apply single advisories"));
+    }
+
+    /// Uninstalls the dependencies that no installed package needs anymore
+    fn autoremove(&self, _dry_run: bool) -> Result<PackagesResponse, PackagesError> {
+        return Err(unsupported(self.manager(), "remove unused dependencies"));
+    }
+
+    /// Empties the cache of the package manager, the freed bytes are the uninstalled_packages_size
+    fn clean(&self) -> Result<PackagesResponse, PackagesError> {
+        return Err(unsupported(self.manager(), "clean its cache"));
+    }
+
+    /// Gets the packages a package depends on
+    fn depends(&self, _package: &str) -> Result<PackagesResponse, PackagesError> {
+        return Err(unsupported(self.manager(), "show dependencies"));
+    }
+
+    /// Gets the installed packages that depend on a package
+    fn rdepends(&self, _package: &str) -> Result<PackagesResponse, PackagesError> {
+        return Err(unsupported(self.manager(), "show reverse dependencies"));
+    }
+
+    /// Gets the installed packages that own a file, with the path in their files
+    fn owner(&self, _path: &str) -> Result<PackagesResponse, PackagesError> {
+        return Err(unsupported(self.manager(), "find the owners of files"));
+    }
+
+    /// Gets an installed package with the files it installs
+    fn files(&self, _package: &str) -> Result<PackagesResponse, PackagesError> {
+        return Err(unsupported(self.manager(), "list the files of packages"));
+    }
+
+    /// Checks the installed files of packages, or of every package if none are given,
+    /// against the package database and returns the files that differ
+    fn verify(&self, _packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        return Err(unsupported(self.manager(), "verify packages"));
+    }
+
+    /// Gets the names of the packages that were installed explicitly, not as a dependency
+    fn user_installed(&self) -> Result<Vec<String>, PackagesError> {
+        return Err(unsupported(
+            self.manager(),
+            "tell which packages were installed explicitly",
+        ));
+    }
 }
 
-pub struct InfoArgs {
-    command: &'static [&'static str],
-    parser: fn(&String) -> PackagesResponse,
+/// Errors from package operations
+pub type PackagesError = SysadminError;
+
+/// Parses the output of a package manager into a PackagesResponse
+pub type Parser = fn(&str) -> Result<PackagesResponse, PackagesError>;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PackagesResponse {
+    pub subcommand: &'static str,
+    pub messages: Vec<String>,
+    pub packages: Vec<Package>,
+    pub packages_size: Option<String>,
+    pub packages_length: Option<usize>,
+    pub uninstalled_packages: Vec<Package>,
+    pub uninstalled_packages_size: Option<String>,
+    pub uninstalled_packages_length: Option<usize>,
+    pub packages_size_bytes: Option<u64>,
+    pub uninstalled_packages_size_bytes: Option<u64>,
+    /// Result of every source when the response combines several sources ("--all-sources")
+    pub sources: Vec<SourceResponse>,
+    /// Past transactions of the package manager, newest first ("history")
+    pub transactions: Vec<Transaction>,
+    pub repositories: Vec<Repository>,
+    pub advisories: Vec<Advisory>,
+    /// Files that differ from the packages they are from ("verify")
+    pub findings: Vec<Finding>,
+    /// Raw output of the package manager, used for the "stdout" output type
+    #[serde(skip)]
+    pub out: String,
 }
 
-pub struct SearchArgs {
-    command: &'static [&'static str],
-    parser: fn(&String) -> PackagesResponse,
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Package {
+    pub name: String,
+    pub arch: String,
+    pub version: Option<String>,
+    pub old_version: Option<String>,
+    pub repository: Option<String>,
+    pub size: Option<String>,
+    pub download: Option<String>,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    pub license: Option<String>,
+    pub installed: bool,
+    pub size_bytes: Option<u64>,
+    pub download_bytes: Option<u64>,
+    /// Flatpak installation the package is in, "system" or "user"
+    pub installation: Option<String>,
+    /// Snap channel the package is tracking or published in, e.g. "latest/stable"
+    pub channel: Option<String>,
+    /// Snap confinement, "strict", "classic" or "devmode"
+    pub confinement: Option<String>,
+    /// Package manager the package is from when the response combines several sources, e.g. "flatpak"
+    pub source: Option<String>,
+    /// Advisories an update of the package fixes, set when updating with "--security" or "--advisory"
+    pub advisories: Vec<Advisory>,
+    /// Whether the package was installed explicitly rather than as a dependency, set by "depends", "rdepends" and "why"
+    pub user_installed: Option<bool>,
+    /// Installed packages that need the package, down to the explicitly installed ones ("why")
+    pub required_by: Vec<Package>,
+    /// Files the package installs ("files"), or the path it was found as the owner of ("owner")
+    pub files: Vec<String>,
 }
 
-pub struct RefreshArgs {
-    command: &'static [&'static str],
-    parser: fn(&String) -> PackagesResponse,
+/// A pending advisory, e.g. a security fix, and the updates that apply it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Advisory {
+    /// e.g. "FEDORA-2024-1a2b3c4d5e". Debian has no ids in its repositories, so the id of an
+    /// update from a security suite is the package and the suite, e.g. "curl/bookworm-security"
+    pub id: String,
+    /// e.g. "security", "bugfix" or "enhancement"
+    pub kind: Option<String>,
+    /// e.g. "Moderate" or "important", as the package manager names it
+    pub severity: Option<String>,
+    pub title: Option<String>,
+    /// Release date as "YYYY-MM-DD HH:MM:SS"
+    pub issued: Option<String>,
+    pub cves: Vec<String>,
+    /// Updates that apply the advisory. Empty in the advisories of a Package
+    pub packages: Vec<Package>,
 }
 
-pub struct UpdateArgs {
-    commands: &'static [&'static [&'static str]],
-    fake: &'static str,
-    parser: fn(&String) -> PackagesResponse,
+/// The advisories an update applies, from "--security" or "--advisory"
+pub enum AdvisoryFilter {
+    Security,
+    Id(String),
 }
 
-pub struct InstallArgs {
-    command: &'static [&'static str],
-    parser: fn(&String) -> PackagesResponse,
+impl AdvisoryFilter {
+    fn matches(&self, advisory: &Advisory) -> bool {
+        return match self {
+            AdvisoryFilter::Security => advisory.kind.as_deref() == Some("security"),
+            AdvisoryFilter::Id(id) => &advisory.id == id,
+        };
+    }
 }
 
-pub struct UninstallArgs {
-    command: &'static [&'static str],
-    parser: fn(&String) -> PackagesResponse,
+/// A file that differs from the package it is from, found by "verify"
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Finding {
+    pub path: String,
+    /// Package the file is from, None when the whole system was verified
+    pub package: Option<String>,
+    /// Whether the file is a configuration file, which are usually changed on purpose
+    pub config: bool,
+    /// What differs, any of "size", "mode", "checksum", "device", "link", "owner", "group",
+    /// "mtime", "capabilities" and "xattrs", or "missing" and "added" for files that
+    /// are gone or that no package has
+    pub problems: Vec<String>,
 }
 
+/// A past transaction of a package manager
 #[derive(Serialize, Deserialize, Debug)]
-pub struct PackagesResponse {
-    subcommand: &'static str,
-    messages: Vec<String>,
-    packages: Vec<Package>,
-    packages_size: Option<String>,
-    packages_length: Option<usize>,
-    uninstalled_packages: Vec<Package>,
-    uninstalled_packages_size: Option<String>,
-    uninstalled_packages_length: Option<usize>,
+pub struct Transaction {
+    /// e.g. "12". Package managers without ids (apt, apk, zypper) number the
+    /// transactions of their log from the oldest one, starting with 1
+    pub id: String,
+    /// Start of the transaction as "YYYY-MM-DD HH:MM:SS"
+    pub timestamp: Option<String>,
+    pub user: Option<String>,
+    /// The kinds of changes in the order they appear, e.g. "install" or "upgrade, remove"
+    pub action: String,
+    /// Command line that started the transaction
+    pub command: Option<String>,
+    /// Changed packages, "installed" is false for removed ones and
+    /// "old_version" is set for upgrades and downgrades
+    pub packages: Vec<Package>,
 }
 
+impl Transaction {
+    pub fn new(id: String) -> Transaction {
+        return Transaction {
+            id,
+            timestamp: None,
+            user: None,
+            action: String::new(),
+            command: None,
+            packages: vec![],
+        };
+    }
+
+    /// Adds a kind of change to the action if it isn't in it yet, e.g. "upgrade"
+    fn add_action(&mut self, action: &str) {
+        if self.action.split(", ").any(|a| a == action) {
+            return;
+        }
+        if !self.action.is_empty() {
+            self.action.push_str(", ");
+        }
+        self.action.push_str(action);
+    }
+}
+
+/// A repository in the configuration of a package manager
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Repository {
+    /// e.g. "fedora". apt repositories are named after their file, with the number
+    /// of the entry if the file has several, e.g. "sources:2". apk repositories
+    /// are their URL or tag, e.g. "testing" for "@testing https://..."
+    pub id: String,
+    pub name: Option<String>,
+    /// Base URLs, mirror lists and metalinks of the repository
+    pub urls: Vec<String>,
+    pub enabled: bool,
+    /// Whether the signatures of packages are checked, None if the configuration doesn't tell
+    pub gpgcheck: Option<bool>,
+    pub priority: Option<i32>,
+    /// Suites of an apt repository, e.g. "bookworm"
+    pub suites: Vec<String>,
+    /// Components of an apt repository, e.g. "main"
+    pub components: Vec<String>,
+    /// Configuration file the repository is in
+    pub file: Option<String>,
+}
+
+/// Result of one source of a response that combines several sources
 #[derive(Serialize, Deserialize, Debug)]
-pub struct Package {
-    name: String,
-    arch: String,
-    version: Option<String>,
-    old_version: Option<String>,
-    repository: Option<String>,
-    size: Option<String>,
-    download: Option<String>,
-    description: Option<String>,
-    url: Option<String>,
-    license: Option<String>,
-    installed: bool,
-}
-
-fn match_package_manager(os_id: &String) -> Option<&'static PackageManager> {
-    match os_id.as_str() {
-        "fedora" | "ultramarine" => Some(&dnf::DNF),
+pub struct SourceResponse {
+    /// Package manager of the source, e.g. "dnf"
+    pub source: String,
+    pub exit_code: i32,
+    pub messages: Vec<String>,
+    pub packages_length: Option<usize>,
+    pub uninstalled_packages_length: Option<usize>,
+    pub error_message: Option<String>,
+    pub error_kind: Option<ErrorKind>,
+}
+
+impl PackagesResponse {
+    /// Sets the human readable sizes (e.g. "12.30 MiB") from the sizes in bytes.
+    /// Backends only set the sizes in bytes, this is done once the response is complete
+    pub fn humanize_sizes(&mut self) {
+        self.packages_size = self.packages_size_bytes.map(simplify_byte_size);
+        self.uninstalled_packages_size =
+            self.uninstalled_packages_size_bytes.map(simplify_byte_size);
+        for package in self
+            .packages
+            .iter_mut()
+            .chain(self.uninstalled_packages.iter_mut())
+        {
+            package.size = package.size_bytes.map(simplify_byte_size);
+            package.download = package.download_bytes.map(simplify_byte_size);
+        }
+    }
+}
+
+/// Gets the backend for an OS id, either the ID or one of the ID_LIKE ids of "/etc/os-release"
+pub fn match_package_manager(os_id: &str) -> Option<&'static dyn PackageBackend> {
+    match os_id {
+        "fedora" | "ultramarine" | "rhel" | "centos" | "rocky" | "almalinux" => Some(&dnf::DNF),
 
         "alpine" | "wolfi" => Some(&apk::APK),
 
-        "debian" | "ubuntu" | "pop" => Some(&apt::APT),
+        "debian" | "ubuntu" | "pop" | "linuxmint" | "kali" => Some(&apt::APT),
 
-        "opensuse-tumbleweed" | "opensuse-leap" => Some(&zypper::ZYPPER),
+        "opensuse-tumbleweed" | "opensuse-leap" | "opensuse" | "suse" | "sles" => {
+            Some(&zypper::ZYPPER)
+        }
+
+        "arch" | "endeavouros" | "manjaro" => Some(&pacman::PACMAN),
 
         &_ => None,
     }
 }
 
-pub fn match_packages_command(
-    os_id: &String,
+/// Gets the backend chosen with "--backend"
+fn chosen_backend(backend: Backend) -> &'static dyn PackageBackend {
+    return match backend {
+        Backend::Apk => &apk::APK,
+        Backend::Apt => &apt::APT,
+        Backend::Dnf => &dnf::DNF,
+        Backend::Pacman => &pacman::PACMAN,
+        Backend::Zypper => &zypper::ZYPPER,
+    };
+}
+
+/// Finds a backend by looking for its package manager in PATH, for distributions
+/// that are neither known by their ID nor by their ID_LIKE
+fn probe_package_manager() -> Option<&'static dyn PackageBackend> {
+    let backends: [&'static dyn PackageBackend; 5] = [
+        &apt::APT,
+        &dnf::DNF,
+        &zypper::ZYPPER,
+        &pacman::PACMAN,
+        &apk::APK,
+    ];
+    return backends
+        .into_iter()
+        .find(|backend| in_path(backend.manager()));
+}
+
+/// Checks if a program is in one of the directories of PATH
+fn in_path(program: &str) -> bool {
+    let Some(path) = env::var_os("PATH") else {
+        return false;
+    };
+    return env::split_paths(&path).any(|dir| dir.join(program).is_file());
+}
+
+/// Chooses the backend from "--backend", then the ID and ID_LIKE ids of
+/// "/etc/os-release" and finally by probing which package manager is installed
+pub fn select_backend(
+    backend: Option<Backend>,
+) -> Result<&'static dyn PackageBackend, PackagesError> {
+    if let Some(backend) = backend {
+        return Ok(chosen_backend(backend));
+    }
+    let os = OsRelease::read();
+    if let Some(backend) = os
+        .as_ref()
+        .ok()
+        .and_then(|os| os.ids().find_map(match_package_manager))
+    {
+        return Ok(backend);
+    }
+    if let Some(backend) = probe_package_manager() {
+        return Ok(backend);
+    }
+    return match os {
+        Ok(os) => Err(SysadminError::UnsupportedOs { os_id: os.id }),
+        Err(error) => Err(error),
+    };
+}
+
+/// Gets the backend of the "--source" of a packages command
+fn source_backend(
+    packagescli: &PackagesArgs,
+) -> Result<&'static dyn PackageBackend, PackagesError> {
+    return match packagescli.source {
+        Source::Native => select_backend(packagescli.backend),
+        Source::Flatpak if packagescli.user => Ok(&flatpak::FLATPAK_USER),
+        Source::Flatpak => Ok(&flatpak::FLATPAK),
+        Source::Snap => Ok(&snap::SNAP),
+    };
+}
+
+/// Gets the backends of every source installed on this host, the native one first
+fn installed_sources(
+    packagescli: &PackagesArgs,
+) -> Result<Vec<&'static dyn PackageBackend>, PackagesError> {
+    let mut backends: Vec<&'static dyn PackageBackend> = vec![];
+    let native = select_backend(packagescli.backend);
+    if let Ok(backend) = native {
+        backends.push(backend);
+    }
+    if in_path("flatpak") {
+        match packagescli.user {
+            true => backends.push(&flatpak::FLATPAK_USER),
+            false => backends.push(&flatpak::FLATPAK),
+        }
+    }
+    if in_path("snap") {
+        backends.push(&snap::SNAP);
+    }
+    if let (true, Err(error)) = (backends.is_empty(), native) {
+        return Err(error);
+    }
+    return Ok(backends);
+}
+
+pub fn match_packages_command(packagescli: &PackagesArgs, output_type: OutputType) -> Exit {
+    if packagescli.all_sources {
+        return run_all_sources(packagescli, output_type);
+    }
+    match source_backend(packagescli) {
+        Ok(backend) => return run_packages_command(backend, packagescli, output_type),
+        Err(error) => return Exit::from_error("none", "Failed to change packages", error),
+    }
+}
+
+/// Runs a "packages" subcommand with the given backend
+pub fn run_packages_command(
+    backend: &dyn PackageBackend,
     packagescli: &PackagesArgs,
     output_type: OutputType,
 ) -> Exit {
-    let manager: &PackageManager;
-    let mut args: Vec<&str>;
-    let terms: &Option<Vec<String>>;
-    let fail_message: &str;
-    let parser: fn(&String) -> PackagesResponse;
-
-    match match_package_manager(os_id) {
-        Some(x) => manager = x,
-        None => {
+    let (result, fail_message) = run_operation(backend, &packagescli.command);
+    match result {
+        Ok(mut response) => {
+            response.humanize_sizes();
             return Exit {
-                exit_code: 1,
-                real_command: "none",
-                out: "Failed to change packages".to_string(),
-                packages_response: None,
-                error_message: Some(format!(
-                    "This operating system ({}) is not supported",
-                    os_id
-                )),
-            }
+                exit_code: 0,
+                real_command: backend.manager(),
+                out: std::mem::take(&mut response.out),
+                packages_response: match output_type {
+                    OutputType::Stdout => None,
+                    _ => Some(response),
+                },
+                error_message: None,
+                error_kind: None,
+                os_info: None,
+            };
         }
+        Err(error) => return Exit::from_error(backend.manager(), fail_message, error),
     }
+}
 
-    match &packagescli.command {
+/// Runs the operation of a "packages" subcommand, returning its result and the message for when it fails
+fn run_operation(
+    backend: &dyn PackageBackend,
+    command: &PackagesCommands,
+) -> (Result<PackagesResponse, PackagesError>, &'static str) {
+    match command {
         PackagesCommands::List {
             installed,
             available,
             extras,
             packages,
         } => {
-            args = manager.list.command.to_vec();
-            if *installed {
-                args.push(&manager.list.installed)
-            }
-            if *available {
-                args.push(&manager.list.available)
-            }
-            if *extras {
-                args.push(&manager.list.extras)
-            }
-            terms = packages;
-            fail_message = "Failed to list packages";
-            parser = manager.list.parser;
-
-            // let mut exit = process_command(
-            //     manager.manager,
-            //     args,
-            //     packages,
-            //     "Failed to list packages",
-            //     manager.list.parser,
-            // );
-            // if exit.exit_code == 0 {
-            //     let res = exit.packages_response.as_mut().unwrap();
-            //     res.packages_length = Some(res.packages.len());
-            // }
-            // return exit;
+            return (
+                backend.list(*installed, *available, *extras, terms(packages)),
+                "Failed to list packages",
+            );
         }
 
         PackagesCommands::Info { packages } => {
-            args = manager.info.command.to_vec();
-            terms = packages;
-            fail_message = "Failed to get info for package(s)";
-            parser = manager.info.parser;
-
-            // let mut exit = process_command(
-            //     manager.manager,
-            //     manager.info.command.to_vec(),
-            //     packages,
-            //     "Failed to get info for package(s)",
-            //     manager.info.parser,
-            // );
-            // if exit.exit_code == 0 {
-            //     let res = exit.packages_response.as_mut().unwrap();
-            //     res.packages_length = Some(res.packages.len());
-            // }
-            // return exit;
+            return (
+                backend.info(terms(packages)),
+                "Failed to get info for package(s)",
+            );
         }
 
         PackagesCommands::Search { search_terms } => {
-            args = manager.search.command.to_vec();
-            terms = search_terms;
-            fail_message = "Failed to search for package(s)";
-            parser = manager.search.parser;
+            return (
+                backend.search(terms(search_terms)),
+                "Failed to search for package(s)",
+            );
         }
 
         PackagesCommands::Refresh => {
-            args = manager.refresh_data.command.to_vec();
-            terms = &None;
-            fail_message = "Failed to refresh packages data";
-            parser = manager.refresh_data.parser;
-        }
-
-        PackagesCommands::Update { fake } => {
-            let mut exit = Exit {
-                exit_code: 1,
-                real_command: "none",
-                error_message: Some(format!(
-                    "\"update\" is not implemented for {}",
-                    manager.manager
-                )),
-                out: "Failed to update packages".to_string(),
-                packages_response: None,
-            };
-            for command in manager.update.commands {
-                let mut commvec = command.to_vec();
-                if *fake {
-                    if command == manager.update.commands.last().unwrap() {
-                        commvec.push(manager.update.fake);
-                    }
+            return (backend.refresh(), "Failed to refresh packages data");
+        }
+
+        PackagesCommands::Update {
+            fake,
+            dry_run,
+            security,
+            advisory,
+        } => {
+            let filter = match (security, advisory) {
+                (_, Some(id)) => AdvisoryFilter::Id(id.clone()),
+                (true, None) => AdvisoryFilter::Security,
+                (false, None) => {
+                    return (
+                        dry_run_result(backend.update(*fake, *dry_run), *dry_run),
+                        "Failed to update packages",
+                    );
                 }
-                exit = process_command(
-                    manager.manager,
-                    commvec,
-                    &None,
-                    "Failed to update packages",
-                    manager.update.parser,
-                    output_type,
+            };
+            return (
+                dry_run_result(update_advisories(backend, &filter, *dry_run), *dry_run),
+                "Failed to update packages",
+            );
+        }
+
+        PackagesCommands::Install { packages, dry_run } => {
+            return (
+                dry_run_result(backend.install(terms(packages), *dry_run), *dry_run),
+                "Failed to install package(s)",
+            );
+        }
+
+        PackagesCommands::Uninstall { packages, dry_run } => {
+            return (
+                dry_run_result(backend.uninstall(terms(packages), *dry_run), *dry_run),
+                "Failed to uninstall package(s)",
+            );
+        }
+
+        PackagesCommands::History {
+            command: Some(HistoryCommands::Undo { id }),
+            ..
+        } => {
+            return (backend.undo(id), "Failed to undo transaction");
+        }
+
+        PackagesCommands::History {
+            command: None,
+            limit,
+        } => {
+            return (backend.history(*limit), "Failed to get package history");
+        }
+
+        PackagesCommands::Repos { command } => match command {
+            None | Some(ReposCommands::List) => {
+                return (backend.repos(), "Failed to list repositories");
+            }
+            Some(ReposCommands::Enable { id }) => {
+                return (backend.enable_repo(id, true), "Failed to enable repository");
+            }
+            Some(ReposCommands::Disable { id }) => {
+                return (
+                    backend.enable_repo(id, false),
+                    "Failed to disable repository",
                 );
-                if exit.exit_code != 0 {
-                    return exit;
-                }
             }
-            return exit;
+            Some(ReposCommands::Add {
+                urls,
+                id,
+                name,
+                disabled,
+                no_gpgcheck,
+                priority,
+                suites,
+                components,
+            }) => {
+                let repo = Repository {
+                    id: id.clone().unwrap_or_default(),
+                    name: name.clone(),
+                    urls: urls.clone(),
+                    enabled: !disabled,
+                    gpgcheck: Some(!no_gpgcheck),
+                    priority: *priority,
+                    suites: suites.clone(),
+                    components: components.clone(),
+                    file: None,
+                };
+                return (backend.add_repo(&repo), "Failed to add repository");
+            }
+            Some(ReposCommands::Remove { id }) => {
+                return (backend.remove_repo(id), "Failed to remove repository");
+            }
+        },
+
+        PackagesCommands::Hold { packages } => {
+            return (backend.hold(packages), "Failed to hold package(s)");
         }
 
-        PackagesCommands::Install { packages } => {
-            args = manager.install.command.to_vec();
-            terms = packages;
-            fail_message = "Failed to install package(s)";
-            parser = manager.install.parser;
+        PackagesCommands::Unhold { packages } => {
+            return (backend.unhold(packages), "Failed to release package(s)");
         }
 
-        PackagesCommands::Uninstall { packages } => {
-            args = manager.uninstall.command.to_vec();
-            terms = packages;
-            fail_message = "Failed to uninstall package(s)";
-            parser = manager.uninstall.parser;
+        PackagesCommands::Holds => {
+            return (backend.holds(), "Failed to list held packages");
         }
-    }
 
-    return process_command(
-        manager.manager,
-        args,
-        terms,
-        fail_message,
-        parser,
-        output_type,
-    );
-}
+        PackagesCommands::Advisories { security } => {
+            let result = backend.advisories().map(|mut res| {
+                if *security {
+                    res.advisories
+                        .retain(|advisory| AdvisoryFilter::Security.matches(advisory));
+                }
+                if res.advisories.is_empty() && res.messages.is_empty() {
+                    res.messages.push("No advisories pending".to_string());
+                }
+                return res;
+            });
+            return (result, "Failed to list advisories");
+        }
 
-fn process_command(
-    package_manager: &'static str,
-    args: Vec<&str>,
-    terms: &Option<Vec<String>>,
-    fail_message: &str,
-    output_parser: fn(&String) -> PackagesResponse,
-    output_type: OutputType,
-) -> Exit {
-    let full_command = Command::new(package_manager)
-        .args(args)
-        .args(terms.as_deref().unwrap_or_default())
-        .output()
-        .expect(fail_message);
-
-    let code = full_command.status.code().unwrap_or(1);
-    match code {
-        0 => {
-            match String::from_utf8(full_command.stdout.to_owned()) {
-                Ok(output) => {
-                    let mut response: Option<PackagesResponse> = None;
-                    if output_type != OutputType::Stdout {
-                        response = Some(output_parser(&output));
-                    }
-                    return Exit {
-                        exit_code: code,
-                        real_command: package_manager,
-                        out: output.to_owned(),
-                        packages_response: response,
-                        error_message: None,
-                    };
+        PackagesCommands::Autoremove { dry_run } => {
+            return (
+                dry_run_result(backend.autoremove(*dry_run), *dry_run),
+                "Failed to remove unused dependencies",
+            );
+        }
+
+        PackagesCommands::Clean => {
+            return (backend.clean(), "Failed to clean the package cache");
+        }
+
+        PackagesCommands::Depends { package } => {
+            let result = backend.depends(package).and_then(|mut res| {
+                mark_user_installed(backend, &mut res.packages)?;
+                if res.packages.is_empty() {
+                    res.messages
+                        .push(format!("{} has no dependencies", package));
                 }
-                Err(_) => {
-                    return Exit {
-                        exit_code: code,
-                        real_command: package_manager,
-                        out: "No readable output".to_string(),
-                        packages_response: None,
-                        error_message: Some("Operation was successful, but the output could not be read from the native package manager".to_string()),
-                    };
+                return Ok(res);
+            });
+            return (result, "Failed to get dependencies");
+        }
+
+        PackagesCommands::Rdepends { package } => {
+            let result = backend.rdepends(package).and_then(|mut res| {
+                mark_user_installed(backend, &mut res.packages)?;
+                if res.packages.is_empty() {
+                    res.messages
+                        .push(format!("No installed package depends on {}", package));
                 }
-            };
+                return Ok(res);
+            });
+            return (result, "Failed to get reverse dependencies");
+        }
+
+        PackagesCommands::Owner { path } => {
+            let result = found(backend.owner(path), format!("No package owns {}", path));
+            return (result, "Failed to find the owner of file");
+        }
+
+        PackagesCommands::Files { package } => {
+            let result = found(
+                backend.files(package),
+                format!("Package {} is not installed", package),
+            );
+            return (result, "Failed to list files of package");
+        }
+
+        PackagesCommands::Verify { packages } => {
+            let result = backend.verify(terms(packages)).map(|mut res| {
+                res.messages.push(match res.findings.len() {
+                    0 => "All files match their packages".to_string(),
+                    1 => "1 file differs from its package".to_string(),
+                    count => format!("{} files differ from their packages", count),
+                });
+                return res;
+            });
+            return (result, "Failed to verify package(s)");
+        }
+
+        PackagesCommands::Why { package } => {
+            return (
+                why(backend, package),
+                "Failed to find why the package is installed",
+            );
+        }
+    }
+}
+
+/// Applies the pending advisories that match a filter and adds them to the packages they update
+fn update_advisories(
+    backend: &dyn PackageBackend,
+    filter: &AdvisoryFilter,
+    dry_run: bool,
+) -> Result<PackagesResponse, PackagesError> {
+    let mut advisories = backend.advisories()?.advisories;
+    advisories.retain(|advisory| filter.matches(advisory));
+    if advisories.is_empty() {
+        if let AdvisoryFilter::Id(id) = filter {
+            return Err(SysadminError::NotFound {
+                message: format!("No pending advisory with the id {}", id),
+            });
+        }
+        let mut res = response("update");
+        res.messages
+            .push("No security updates available".to_string());
+        return Ok(res);
+    }
+    let mut res = backend.update_advisories(filter, &advisories, dry_run)?;
+    for package in res.packages.iter_mut() {
+        for advisory in advisories.iter() {
+            if advisory
+                .packages
+                .iter()
+                .any(|fixed| fixed.name == package.name)
+            {
+                package.advisories.push(Advisory {
+                    packages: vec![],
+                    ..advisory.clone()
+                });
+            }
+        }
+    }
+    return Ok(res);
+}
+
+/// Response of a hold or unhold: the packages held after it, with the output of the package manager
+fn held(
+    mut res: PackagesResponse,
+    subcommand: &'static str,
+    packages: &[String],
+    output: String,
+) -> Result<PackagesResponse, PackagesError> {
+    res.subcommand = subcommand;
+    res.messages = match subcommand {
+        "hold" => vec![format!("Held {}", packages.join(", "))],
+        _ => vec![format!("Released {}", packages.join(", "))],
+    };
+    res.out = output;
+    return Ok(res);
+}
+
+/// Sets whether packages were installed explicitly, if the backend can tell
+fn mark_user_installed(
+    backend: &dyn PackageBackend,
+    packages: &mut [Package],
+) -> Result<(), PackagesError> {
+    // Backends that can't tell leave user_installed out, the packages are still useful
+    let user_installed = match backend.user_installed() {
+        Ok(user_installed) => user_installed,
+        Err(SysadminError::UnsupportedOperation { .. }) => return Ok(()),
+        Err(error) => return Err(error),
+    };
+    for package in packages.iter_mut() {
+        package.user_installed = Some(user_installed.contains(&package.name));
+    }
+    return Ok(());
+}
+
+/// Finds why a package is installed: the package with the tree of installed packages
+/// that need it, every branch ends at an explicitly installed package
+fn why(backend: &dyn PackageBackend, package: &str) -> Result<PackagesResponse, PackagesError> {
+    let user_installed = backend.user_installed()?;
+    let mut res = response("why");
+    let mut root = Package {
+        name: package.to_string(),
+        installed: true,
+        user_installed: Some(user_installed.iter().any(|name| name == package)),
+        ..Default::default()
+    };
+    if root.user_installed == Some(true) {
+        res.messages
+            .push(format!("{} was installed explicitly", package));
+    } else {
+        let mut seen = vec![package.to_string()];
+        root.required_by = required_by(backend, package, &user_installed, &mut seen)?;
+        if root.required_by.is_empty() {
+            res.messages.push(format!(
+                "No installed package depends on {}, it can be autoremoved",
+                package
+            ));
+        }
+    }
+    res.packages.push(root);
+    return Ok(res);
+}
+
+/// Gets the installed packages that need a package, and the ones that need them until
+/// an explicitly installed package. Packages that were seen already aren't followed again
+fn required_by(
+    backend: &dyn PackageBackend,
+    package: &str,
+    user_installed: &[String],
+    seen: &mut Vec<String>,
+) -> Result<Vec<Package>, PackagesError> {
+    let mut packages = backend.rdepends(package)?.packages;
+    for dependent in packages.iter_mut() {
+        dependent.user_installed = Some(user_installed.contains(&dependent.name));
+        if dependent.user_installed == Some(true) || seen.contains(&dependent.name) {
+            continue;
         }
+        seen.push(dependent.name.clone());
+        dependent.required_by = required_by(backend, &dependent.name, user_installed, seen)?;
+    }
+    return Ok(packages);
+}
+
+/// Gives a query that found no package the same NotFound error on every backend.
+/// Some package managers succeed without output when they find nothing, see run_query for the others
+fn found(
+    result: Result<PackagesResponse, PackagesError>,
+    message: String,
+) -> Result<PackagesResponse, PackagesError> {
+    return match result {
+        Ok(res) if res.packages.is_empty() => Err(SysadminError::NotFound { message }),
+        Err(SysadminError::NotFound { .. }) => Err(SysadminError::NotFound { message }),
+        result => result,
+    };
+}
+
+/// Response of an autoremove from the parsed uninstall of the unused packages
+fn autoremoved(mut res: PackagesResponse) -> PackagesResponse {
+    res.subcommand = "autoremove";
+    if res.uninstalled_packages.is_empty() {
+        res.messages = vec!["No unused packages to remove".to_string()];
+    }
+    return res;
+}
+
+/// Runs the command that cleans the cache of a package manager and measures the bytes it freed
+/// in the given directories of the cache
+fn clean_cache(command: &mut Command, dirs: &[&str]) -> Result<PackagesResponse, PackagesError> {
+    let before = cache_size(dirs);
+    let output = run_command(command)?;
+    let freed = before.saturating_sub(cache_size(dirs));
+    let mut res = response("clean");
+    res.messages.push(match freed {
+        0 => "The cache is clean already".to_string(),
+        _ => format!("Freed {}", simplify_byte_size(freed)),
+    });
+    res.uninstalled_packages_size_bytes = Some(freed);
+    res.out = output;
+    return Ok(res);
+}
+
+fn dry_run_result(
+    result: Result<PackagesResponse, PackagesError>,
+    dry_run: bool,
+) -> Result<PackagesResponse, PackagesError> {
+    let mut res = result?;
+    if dry_run {
+        res.messages
+            .push("Dry run, nothing was changed".to_string());
+    }
+    return Ok(res);
+}
+
+/// Gets the error for a backend that can't do an operation, e.g. "show its transaction history"
+fn unsupported(manager: &str, operation: &str) -> PackagesError {
+    return SysadminError::UnsupportedOperation {
+        message: format!("{} can't {}", manager, operation),
+    };
+}
+
+/// Runs a "packages" subcommand with every installed source and combines their responses.
+/// Sources that fail don't stop the others, their errors are in the "sources" of the response
+fn run_all_sources(packagescli: &PackagesArgs, output_type: OutputType) -> Exit {
+    let (subcommand, fail_message) = match packagescli.command {
+        PackagesCommands::List { .. } => ("list", "Failed to list packages"),
+        PackagesCommands::Search { .. } => ("search", "Failed to search for package(s)"),
+        PackagesCommands::Update { .. } => ("update", "Failed to update packages"),
         _ => {
-            return Exit {
-                exit_code: code,
-                real_command: package_manager,
-                out: fail_message.to_string(),
-                packages_response: None,
-                error_message: Some(String::from_utf8(full_command.stderr).unwrap_or_default()),
+            let error = SysadminError::UnsupportedOperation {
+                message: "--all-sources only works with list, search and update".to_string(),
             };
+            return Exit::from_error("none", "Failed to change packages", error);
+        }
+    };
+    let backends = match installed_sources(packagescli) {
+        Ok(backends) => backends,
+        Err(error) => return Exit::from_error("none", fail_message, error),
+    };
+
+    let mut combined = response(subcommand);
+    let mut failure: Option<(&str, SysadminError)> = None;
+    for backend in backends {
+        let source = backend.manager();
+        let mut res = match run_operation(backend, &packagescli.command).0 {
+            Ok(res) => res,
+            Err(error) => {
+                combined.sources.push(SourceResponse {
+                    source: source.to_string(),
+                    exit_code: error.exit_code(),
+                    messages: vec![],
+                    packages_length: None,
+                    uninstalled_packages_length: None,
+                    error_message: Some(error.to_string()),
+                    error_kind: Some(error.kind()),
+                });
+                failure.get_or_insert((source, error));
+                continue;
+            }
+        };
+        for package in res
+            .packages
+            .iter_mut()
+            .chain(res.uninstalled_packages.iter_mut())
+        {
+            package.source = Some(source.to_string());
+        }
+        combined.messages.extend(
+            res.messages
+                .iter()
+                .map(|message| format!("{}: {}", source, message)),
+        );
+        combined.packages.append(&mut res.packages);
+        combined
+            .uninstalled_packages
+            .append(&mut res.uninstalled_packages);
+        combined.packages_length = add_optional(combined.packages_length, res.packages_length);
+        combined.uninstalled_packages_length = add_optional(
+            combined.uninstalled_packages_length,
+            res.uninstalled_packages_length,
+        );
+        combined.packages_size_bytes =
+            add_optional(combined.packages_size_bytes, res.packages_size_bytes);
+        combined.uninstalled_packages_size_bytes = add_optional(
+            combined.uninstalled_packages_size_bytes,
+            res.uninstalled_packages_size_bytes,
+        );
+        combined.out.push_str(&res.out);
+        combined.sources.push(SourceResponse {
+            source: source.to_string(),
+            exit_code: 0,
+            messages: res.messages,
+            packages_length: res.packages_length,
+            uninstalled_packages_length: res.uninstalled_packages_length,
+            error_message: None,
+            error_kind: None,
+        });
+    }
+    combined.humanize_sizes();
+
+    let (exit_code, error_message, error_kind) = match &failure {
+        Some((source, error)) => (
+            error.exit_code(),
+            Some(format!("{}: {}", source, error)),
+            Some(error.kind()),
+        ),
+        None => (0, None, None),
+    };
+    if let Some(error_message) = &error_message {
+        combined
+            .out
+            .push_str(&format!("{}\n{}", fail_message, error_message));
+    }
+    return Exit {
+        exit_code,
+        real_command: "multiple",
+        out: std::mem::take(&mut combined.out),
+        packages_response: match output_type {
+            OutputType::Stdout => None,
+            _ => Some(combined),
+        },
+        error_message,
+        error_kind,
+        os_info: None,
+    };
+}
+
+/// Adds two optional counts or sizes, a missing one counts as nothing
+fn add_optional<T: std::ops::Add<Output = T>>(a: Option<T>, b: Option<T>) -> Option<T> {
+    return match (a, b) {
+        (Some(a), Some(b)) => Some(a + b),
+        (a, b) => a.or(b),
+    };
+}
+
+fn terms(terms: &Option<Vec<String>>) -> &[String] {
+    return terms.as_deref().unwrap_or_default();
+}
+
+/// Environment variable with a directory to read the package manager logs from instead of "/var/log"
+pub const LOG_DIR_VAR: &str = "SYSADMIN_LOG_DIR";
+
+/// Reads a log file of a package manager, e.g. "apt/history.log" for "/var/log/apt/history.log".
+/// A log that doesn't exist is read as empty, the package manager hasn't done anything yet
+pub fn read_log(path: &str) -> Result<String, PackagesError> {
+    let dir = env::var_os(LOG_DIR_VAR).unwrap_or("/var/log".into());
+    return read_file(&Path::new(&dir).join(path));
+}
+
+/// Environment variable with a directory to read and write the configuration in instead of "/etc"
+pub const ETC_DIR_VAR: &str = "SYSADMIN_ETC_DIR";
+
+/// Gets the path of a configuration file, e.g. "apk/repositories" for "/etc/apk/repositories"
+pub fn etc_path(path: &str) -> PathBuf {
+    let dir = env::var_os(ETC_DIR_VAR).unwrap_or("/etc".into());
+    return Path::new(&dir).join(path);
+}
+
+/// Reads a text file, a file that doesn't exist is read as empty
+pub fn read_file(path: &Path) -> Result<String, PackagesError> {
+    match std::fs::read(path) {
+        Ok(contents) => return Ok(String::from_utf8_lossy(&contents).to_string()),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(String::new()),
+        Err(error) => return Err(SysadminError::spawn(&path.to_string_lossy(), error)),
+    }
+}
+
+/// Writes a configuration file and the directories it is in, or removes it if it would be empty
+pub fn write_file(path: &Path, contents: &str) -> Result<(), PackagesError> {
+    let result = match contents.trim().is_empty() {
+        true => std::fs::remove_file(path),
+        false => std::fs::create_dir_all(path.parent().unwrap_or(Path::new("/")))
+            .and_then(|_| std::fs::write(path, contents)),
+    };
+    return result.map_err(|error| SysadminError::spawn(&path.to_string_lossy(), error));
+}
+
+/// Environment variable with a directory to find the package manager caches in instead of "/var/cache"
+pub const CACHE_DIR_VAR: &str = "SYSADMIN_CACHE_DIR";
+
+/// Gets the size in bytes of the files in directories of the cache, e.g. "apt" for "/var/cache/apt".
+/// Files that can't be read are not counted
+pub fn cache_size(dirs: &[&str]) -> u64 {
+    let root = env::var_os(CACHE_DIR_VAR).unwrap_or("/var/cache".into());
+    let mut size = 0;
+    let mut pending: Vec<PathBuf> = dirs.iter().map(|dir| Path::new(&root).join(dir)).collect();
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            match entry.metadata() {
+                Ok(metadata) if metadata.is_dir() => pending.push(entry.path()),
+                Ok(metadata) if metadata.is_file() => size += metadata.len(),
+                _ => {}
+            }
+        }
+    }
+    return size;
+}
+
+/// Environment variable with the path of an executable that replaces every native program, e.g. a mock for tests
+pub const NATIVE_PATH_VAR: &str = "SYSADMIN_NATIVE_PATH";
+
+/// Creates the Command for a native program like "apt" or "rpm".
+/// If SYSADMIN_NATIVE_PATH is set, that executable is run instead and gets the
+/// name of the program it replaces in SYSADMIN_NATIVE_PROGRAM
+pub fn native_command(program: &str) -> Command {
+    let Some(path) = env::var_os(NATIVE_PATH_VAR) else {
+        return Command::new(program);
+    };
+    let mut command = Command::new(path);
+    command.env("SYSADMIN_NATIVE_PROGRAM", program);
+    return command;
+}
+
+/// Runs a package manager command and returns its output
+pub fn run_command(command: &mut Command) -> Result<String, PackagesError> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let output = execute(command)?;
+    if !output.status.success() {
+        return Err(SysadminError::failed(
+            &program,
+            output.status.code().unwrap_or(1),
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+    match String::from_utf8(output.stdout) {
+        Ok(stdout) => return Ok(stdout),
+        Err(_) => return Err(SysadminError::UnreadableOutput { command: program }),
+    }
+}
+
+/// Runs a query of the package database like "rpm -qf" or "dpkg -L", these fail when they find nothing.
+/// A failure that prints one of the nothing_found messages, on stdout or stderr, is a NotFound error
+pub fn run_query(command: &mut Command, nothing_found: &[&str]) -> Result<String, PackagesError> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let output = execute(command)?;
+    if !output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        let not_found = stdout
+            .lines()
+            .chain(stderr.lines())
+            .find(|line| nothing_found.iter().any(|message| line.contains(message)));
+        if let Some(line) = not_found {
+            return Err(SysadminError::NotFound {
+                message: line.to_string(),
+            });
+        }
+        return Err(SysadminError::failed(
+            &program,
+            output.status.code().unwrap_or(1),
+            stderr,
+        ));
+    }
+    match String::from_utf8(output.stdout) {
+        Ok(stdout) => return Ok(stdout),
+        Err(_) => return Err(SysadminError::UnreadableOutput { command: program }),
+    }
+}
+
+/// Runs a command that answers "no" to its confirmation, like "dnf --assumeno", and
+/// returns the output it printed up to the confirmation.
+/// These fail with the message "Operation aborted" once the transaction is printed
+pub fn run_assumeno(command: &mut Command) -> Result<String, PackagesError> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let output = execute(command)?;
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let aborted = stdout.contains("Operation aborted") || stderr.contains("Operation aborted");
+    if !output.status.success() && !aborted {
+        return Err(SysadminError::failed(
+            &program,
+            output.status.code().unwrap_or(1),
+            stderr,
+        ));
+    }
+    return Ok(stdout);
+}
+
+/// Runs a verification of installed files, like "rpm -V", which exits with 1 when files differ.
+/// Without any output the exit code 1 means the verification itself failed
+pub fn run_verify(command: &mut Command) -> Result<String, PackagesError> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let output = execute(command)?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let differs = output.status.code() == Some(1) && !stdout.trim().is_empty();
+    if !output.status.success() && !differs {
+        return Err(SysadminError::failed(
+            &program,
+            output.status.code().unwrap_or(1),
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+    return Ok(stdout);
+}
+
+/// Verifies packages with a command that checks one package at a time, so the findings
+/// know their package, or the whole system with the arguments for all if no packages are given
+fn verify_packages(
+    program: &str,
+    all: &[&str],
+    each: &[&str],
+    packages: &[String],
+    parser: Parser,
+) -> Result<PackagesResponse, PackagesError> {
+    if packages.is_empty() {
+        let output = run_verify(native_command(program).args(all))?;
+        return parse_output(output, parser);
+    }
+    let mut res = response("verify");
+    for package in packages {
+        let output = run_verify(native_command(program).args(each).arg(package))?;
+        let mut verified = parse_output(output, parser)?;
+        for finding in verified.findings.iter_mut() {
+            finding.package = Some(package.clone());
         }
+        res.findings.append(&mut verified.findings);
+        res.out.push_str(&verified.out);
     }
+    return Ok(res);
+}
+
+/// Runs a command and collects its output, streaming its progress if a sink is set
+fn execute(command: &mut Command) -> Result<Output, PackagesError> {
+    let result = match progress_sink() {
+        Some(sink) => run_streaming(command, sink),
+        None => command.output(),
+    };
+    return result
+        .map_err(|error| SysadminError::spawn(&command.get_program().to_string_lossy(), error));
+}
+
+/// Runs a command like Command::output, but sends a ProgressEvent for every line it prints
+fn run_streaming(command: &mut Command, sink: &ProgressSink) -> io::Result<Output> {
+    let parser = progress_parser(&native_program(command));
+    let on_line = |line: &str| {
+        let event = parser
+            .and_then(|parser| parser(line))
+            .unwrap_or_else(|| ProgressEvent::new(Phase::Output, line));
+        sink(&event);
+    };
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        return Err(io::Error::other("output of the command is not piped"));
+    };
+    let (stdout, stderr) = thread::scope(|scope| {
+        let stderr = scope.spawn(|| read_lines(stderr, on_line));
+        let stdout = read_lines(stdout, on_line);
+        return (stdout, stderr.join());
+    });
+    let status = child.wait()?;
+    return Ok(Output {
+        status,
+        stdout: stdout?,
+        stderr: stderr.unwrap_or_else(|_| Ok(vec![]))?,
+    });
+}
+
+/// Gets the name of the native program of a command, also when SYSADMIN_NATIVE_PATH replaces it
+fn native_program(command: &Command) -> String {
+    for (key, value) in command.get_envs() {
+        if let (true, Some(value)) = (key == "SYSADMIN_NATIVE_PROGRAM", value) {
+            return value.to_string_lossy().to_string();
+        }
+    }
+    return command.get_program().to_string_lossy().to_string();
+}
+
+/// Gets the parser for the progress lines of a native program
+fn progress_parser(program: &str) -> Option<ProgressParser> {
+    return match program {
+        "apk" => Some(apk::parse_progress),
+        "apt" | "apt-get" => Some(apt::parse_progress),
+        "dnf" => Some(dnf::parse_progress),
+        "flatpak" => Some(flatpak::parse_progress),
+        "pacman" => Some(pacman::parse_progress),
+        "snap" => Some(snap::parse_progress),
+        "zypper" => Some(zypper::parse_progress),
+        _ => None,
+    };
+}
+
+/// Parses the output of a package manager, keeping the raw output in the response
+pub fn parse_output(output: String, parser: Parser) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parser(&output)?;
+    res.out = output;
+    return Ok(res);
 }
 
 pub fn response(subcommand: &'static str) -> PackagesResponse {
@@ -318,41 +1307,90 @@ pub fn response(subcommand: &'static str) -> PackagesResponse {
         uninstalled_packages: vec![],
         uninstalled_packages_size: None,
         uninstalled_packages_length: None,
+        packages_size_bytes: None,
+        uninstalled_packages_size_bytes: None,
+        sources: vec![],
+        transactions: vec![],
+        repositories: vec![],
+        advisories: vec![],
+        findings: vec![],
+        out: String::new(),
     };
 }
 
-pub fn simplify_byte_size(num: &str) -> String {
-    let mut size = num.parse::<f64>().unwrap();
+/// Formats seconds since the Unix epoch as "YYYY-MM-DD HH:MM:SS" in UTC
+pub fn format_unix_time(seconds: i64) -> String {
+    let days = seconds.div_euclid(86400);
+    let time = seconds.rem_euclid(86400);
+    // Converts the days to a date of the proleptic Gregorian calendar, with years starting in March
+    let era_days = days + 719468;
+    let era = era_days.div_euclid(146097);
+    let day_of_era = era_days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    return format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    );
+}
+
+/// Formats a size in bytes with binary units, e.g. "12.30 MiB"
+pub fn simplify_byte_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
     let mut unit: &str = "B";
-    if size > 1024.0 {
-        size /= 1024.0;
-        unit = "KiB";
-        if size > 1024.0 {
-            size /= 1024.0;
-            unit = "MiB";
-            if size > 1024.0 {
-                size /= 1024.0;
-                unit = "GiB";
-                if size > 1024.0 {
-                    size /= 1024.0;
-                    unit = "TiB";
-                }
-            }
+    for next in ["KiB", "MiB", "GiB", "TiB", "PiB"] {
+        if size < 1024.0 {
+            break;
         }
+        size /= 1024.0;
+        unit = next;
     }
     return format!("{size:.2} {unit}");
 }
 
-pub fn get_byte_size(num: &str, unit: &str) -> f64 {
-    let mut size = num.parse::<f64>().unwrap();
-    let multiplier: f64 = match unit {
-        "B" => 1.0,
-        "KiB" => 1024.0,
-        "MiB" => 1048576.0,
-        "GiB" => 1073741824.0,
-        "TiB" => 1099511628000.0,
-        _ => 0.0,
+/// Converts a size like "12.3 MiB", "1,446 kB" or "64 k" to bytes.
+/// A size without a unit is in bytes
+pub fn parse_byte_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+        .unwrap_or(size.len());
+    let (num, unit) = size.split_at(split);
+    return get_byte_size(num, unit.trim());
+}
+
+/// Converts a number and its unit to bytes, or None if either can't be understood.
+/// Handles the SI ("kB", "MB") and IEC ("KiB", "MiB") units, and the
+/// single letter binary units of dnf4 ("k", "M")
+pub fn get_byte_size(num: &str, unit: &str) -> Option<u64> {
+    let size = num.replace(",", "").parse::<f64>().ok()?;
+    let multiplier: u64 = match unit {
+        "" | "B" | "bytes" => 1,
+        "KiB" | "k" | "K" => 1 << 10,
+        "MiB" | "M" => 1 << 20,
+        "GiB" | "G" => 1 << 30,
+        "TiB" | "T" => 1 << 40,
+        "PiB" | "P" => 1 << 50,
+        "kB" | "KB" => 1_000,
+        "MB" => 1_000_000,
+        "GB" => 1_000_000_000,
+        "TB" => 1_000_000_000_000,
+        "PB" => 1_000_000_000_000_000,
+        _ => return None,
     };
-    size *= multiplier;
-    return size;
+    return Some((size * multiplier as f64).round() as u64);
 }
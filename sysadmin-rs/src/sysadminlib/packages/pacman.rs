@@ -0,0 +1,465 @@
+use super::*;
+
+pub struct Pacman;
+
+pub static PACMAN: Pacman = Pacman;
+
+impl PackageBackend for Pacman {
+    fn manager(&self) -> &'static str {
+        return "pacman";
+    }
+
+    fn list(
+        &self,
+        installed: bool,
+        available: bool,
+        extras: bool,
+        packages: &[String],
+    ) -> Result<PackagesResponse, PackagesError> {
+        let mut res = response("list");
+        // "-Q" lists installed packages, "-m" limits them to foreign packages
+        if installed || extras || !available {
+            let mut command = native_command("pacman");
+            command.arg("-Q");
+            if extras && !installed {
+                command.arg("-m");
+            }
+            let output = run_command(command.args(packages))?;
+            let mut installed = parse_output(output, parse_list)?;
+            res.packages.append(&mut installed.packages);
+            res.out.push_str(&installed.out);
+        }
+        // "-Sl" lists the packages of the sync databases, it takes repositories rather than packages
+        if available {
+            let output = run_command(native_command("pacman").arg("-Sl"))?;
+            let mut available = parse_output(output, parse_sync_list)?;
+            available.packages.retain(|package| {
+                return !package.installed
+                    && (packages.is_empty() || packages.contains(&package.name));
+            });
+            res.packages.append(&mut available.packages);
+            res.out.push_str(&available.out);
+        }
+        res.packages_length = Some(res.packages.len());
+        return Ok(res);
+    }
+
+    fn info(&self, packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(native_command("pacman").arg("-Si").args(packages))?;
+        return parse_output(output, parse_info);
+    }
+
+    fn search(&self, search_terms: &[String]) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(native_command("pacman").arg("-Ss").args(search_terms))?;
+        return parse_output(output, parse_search);
+    }
+
+    fn refresh(&self) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(native_command("pacman").arg("-Sy"))?;
+        return parse_output(output, parse_refresh);
+    }
+
+    fn update(&self, fake: bool, dry_run: bool) -> Result<PackagesResponse, PackagesError> {
+        if dry_run {
+            // Without "-y", the transaction is resolved against the current databases
+            let output = run_command(native_command("pacman").args([
+                "-Su",
+                "--print",
+                "--print-format",
+                PRINT_FORMAT,
+            ]))?;
+            return parse_output(output, parse_printed_update);
+        }
+        let mut command = native_command("pacman");
+        command.args(["-Syu", "--noconfirm"]);
+        if fake {
+            command.arg("--downloadonly");
+        }
+        let output = run_command(&mut command)?;
+        return parse_output(output, parse_update);
+    }
+
+    fn install(
+        &self,
+        packages: &[String],
+        dry_run: bool,
+    ) -> Result<PackagesResponse, PackagesError> {
+        if dry_run {
+            let output = run_command(
+                native_command("pacman")
+                    .args(["-S", "--needed", "--print", "--print-format", PRINT_FORMAT])
+                    .args(packages),
+            )?;
+            return parse_output(output, parse_printed_install);
+        }
+        let output = run_command(
+            native_command("pacman")
+                .args(["-S", "--needed", "--noconfirm"])
+                .args(packages),
+        )?;
+        return parse_output(output, parse_install);
+    }
+
+    fn uninstall(
+        &self,
+        packages: &[String],
+        dry_run: bool,
+    ) -> Result<PackagesResponse, PackagesError> {
+        if dry_run {
+            let output = run_command(
+                native_command("pacman")
+                    .args(["-Rs", "--print", "--print-format", PRINT_FORMAT])
+                    .args(packages),
+            )?;
+            return parse_output(output, parse_printed_uninstall);
+        }
+        let output = run_command(
+            native_command("pacman")
+                .args(["-Rs", "--noconfirm"])
+                .args(packages),
+        )?;
+        return parse_output(output, parse_uninstall);
+    }
+}
+
+/// Format of the packages a transaction would change with "--print",
+/// the name, version, repository and download size in bytes
+const PRINT_FORMAT: &str = "%n %v %r %s";
+
+/// Gets the parser for the output of a subcommand, used by the fixture tests
+#[cfg(test)]
+pub(super) fn parser(subcommand: &str) -> Option<Parser> {
+    return match subcommand {
+        "list" => Some(parse_list),
+        "sync_list" => Some(parse_sync_list),
+        "info" => Some(parse_info),
+        "search" => Some(parse_search),
+        "refresh" => Some(parse_refresh),
+        "update" => Some(parse_update),
+        "install" => Some(parse_install),
+        "uninstall" => Some(parse_uninstall),
+        "printed_update" => Some(parse_printed_update),
+        "printed_install" => Some(parse_printed_install),
+        "printed_uninstall" => Some(parse_printed_uninstall),
+        _ => None,
+    };
+}
+
+/// Parse list output
+fn parse_list(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("list");
+    for line in output.lines() {
+        // e.g. "bash 5.2.026-2"
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() != 2 {
+            continue;
+        }
+        res.packages.push(Package {
+            name: parts[0].to_string(),
+            version: Some(parts[1].to_string()),
+            installed: true,
+            ..Default::default()
+        });
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Parse "pacman -Sl" output, the packages of every sync database, e.g.
+/// "core bash 5.2.026-2 [installed]", "extra ripgrep 14.1.0-1" or "core curl 8.7.1-1 [installed: 8.6.0-1]"
+fn parse_sync_list(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("list");
+    for line in output.lines() {
+        let (package, flag) = match line.split_once(" [") {
+            Some((package, flag)) => (package, flag.trim_end_matches(']')),
+            None => (line, ""),
+        };
+        let parts: Vec<&str> = package.split_whitespace().collect();
+        if parts.len() != 3 {
+            continue;
+        }
+        res.packages.push(Package {
+            name: parts[1].to_string(),
+            version: Some(parts[2].to_string()),
+            repository: Some(parts[0].to_string()),
+            old_version: flag
+                .strip_prefix("installed: ")
+                .map(|version| version.to_string()),
+            installed: flag.starts_with("installed"),
+            ..Default::default()
+        });
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Parse info output
+fn parse_info(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("info");
+    for part in output.split("\n\n") {
+        let mut package = Package::default();
+        for line in part.lines() {
+            // Continuation lines of multi-value fields start with whitespace
+            let Some((field, value)) = line.split_once(" : ") else {
+                continue;
+            };
+            let value = value.trim().to_string();
+            match field.trim() {
+                "Repository" => package.repository = Some(value),
+                "Name" => package.name = value,
+                "Version" => package.version = Some(value),
+                "Description" => package.description = Some(value),
+                "Architecture" => package.arch = value,
+                "URL" => package.url = Some(value),
+                "Licenses" => package.license = Some(value),
+                "Download Size" => package.download_bytes = parse_byte_size(&value),
+                "Installed Size" => package.size_bytes = parse_byte_size(&value),
+                _ => {}
+            }
+        }
+        if package.name.is_empty() {
+            continue;
+        }
+        res.packages.push(package);
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Parse search output
+fn parse_search(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("search");
+    for line in output.lines() {
+        if line.starts_with(' ') {
+            if let Some(package) = res.packages.last_mut() {
+                package.description = Some(line.trim().to_string());
+            }
+            continue;
+        }
+        // e.g. "extra/bash-completion 2.14.0-2 [installed: 2.13.0-1]" or "core/bash 5.2.026-2 (base) [installed]"
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 2 {
+            continue;
+        }
+        let Some((repository, name)) = parts[0].split_once('/') else {
+            continue;
+        };
+        let (installed, old_version) = match line.split_once(" [installed") {
+            Some((_, rest)) => (
+                true,
+                rest.strip_prefix(": ")
+                    .map(|version| version.trim_end_matches(']').to_string()),
+            ),
+            None => (false, None),
+        };
+        res.packages.push(Package {
+            name: name.to_string(),
+            version: Some(parts[1].to_string()),
+            old_version,
+            repository: Some(repository.to_string()),
+            installed,
+            ..Default::default()
+        });
+    }
+    if res.packages.is_empty() {
+        res.messages.push("Nothing matched search".to_string());
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Parse refresh output
+fn parse_refresh(_output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("refresh");
+    res.messages.push("Updated repos".to_string());
+    return Ok(res);
+}
+
+/// Parse update output
+fn parse_update(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_transaction("update", output, true);
+    res.messages.insert(0, "Updated repos".to_string());
+    if res.packages.is_empty() {
+        res.messages.push("No updates available".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parse install output
+fn parse_install(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_transaction("install", output, true);
+    if res.packages.is_empty() {
+        res.messages
+            .push("Package(s) already installed".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parse uninstall output
+fn parse_uninstall(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_transaction("uninstall", output, false);
+    if res.uninstalled_packages.is_empty() {
+        res.messages.push("No packages to uninstall".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parses the transaction summary pacman prints before applying it, e.g.
+/// "Packages (2) curl-8.7.1-1  libfoo-1.0-1" followed by the total sizes
+fn parse_transaction(subcommand: &'static str, output: &str, installing: bool) -> PackagesResponse {
+    let mut res = response(subcommand);
+    let mut lines = output.lines();
+    let mut targets: Vec<&str> = vec![];
+
+    while let Some(line) = lines.next() {
+        if let Some(rest) = line.strip_prefix("Packages (") {
+            let (_, packages) = rest.split_once(')').unwrap_or_default();
+            targets.extend(packages.split_whitespace());
+            // The list wraps onto indented lines on narrow terminals
+            for line in lines.by_ref() {
+                if !line.starts_with(' ') || line.trim().is_empty() {
+                    break;
+                }
+                targets.extend(line.split_whitespace());
+            }
+            continue;
+        }
+        let Some((field, value)) = line.split_once(':') else {
+            continue;
+        };
+        match field {
+            "Total Installed Size" => res.packages_size_bytes = parse_byte_size(value),
+            "Total Removed Size" => res.uninstalled_packages_size_bytes = parse_byte_size(value),
+            _ => {}
+        }
+    }
+
+    for target in targets {
+        // Targets are "name-version-release", the version itself never contains a "-"
+        let Some(release) = target.rfind('-') else {
+            continue;
+        };
+        let Some(version) = target[..release].rfind('-') else {
+            continue;
+        };
+        let package = Package {
+            name: target[..version].to_string(),
+            version: Some(target[version + 1..].to_string()),
+            installed: installing,
+            ..Default::default()
+        };
+        if installing {
+            res.packages.push(package);
+        } else {
+            res.uninstalled_packages.push(package);
+        }
+    }
+
+    if !res.packages.is_empty() {
+        res.packages_length = Some(res.packages.len());
+    }
+    if !res.uninstalled_packages.is_empty() {
+        res.uninstalled_packages_length = Some(res.uninstalled_packages.len());
+    }
+    return res;
+}
+
+/// Parses a line pacman prints while it runs, e.g. "(1/3) upgrading curl   [######] 100%"
+/// or "downloading curl-8.7.1-1-x86_64.pkg.tar.zst..."
+pub(super) fn parse_progress(line: &str) -> Option<ProgressEvent> {
+    let trimmed = line.trim();
+    if trimmed.starts_with(":: Synchronizing") || trimmed.ends_with(" downloading...") {
+        return Some(ProgressEvent::new(Phase::Refreshing, line));
+    }
+    if let Some(file) = trimmed.strip_prefix("downloading ") {
+        let mut event = ProgressEvent::new(Phase::Downloading, line);
+        event.package = Some(file.trim_end_matches("...").to_string());
+        return Some(event);
+    }
+    if trimmed.starts_with("checking ") || trimmed.starts_with("loading package files") {
+        return Some(ProgressEvent::new(Phase::Verifying, line));
+    }
+    let parts: Vec<&str> = trimmed.split_whitespace().collect();
+    let percent = counter_percent(parts.first()?)?;
+    let (phase, package) = match parts.get(1..3) {
+        Some(["installing", package]) => (Phase::Installing, Some(package)),
+        Some(["upgrading" | "downgrading" | "reinstalling", package]) => {
+            (Phase::Updating, Some(package))
+        }
+        Some(["removing", package]) => (Phase::Removing, Some(package)),
+        Some(["checking" | "loading", _]) => (Phase::Verifying, None),
+        // Hooks, e.g. "(1/2) Arming ConditionNeedsUpdate..."
+        _ => (Phase::Configuring, None),
+    };
+    let mut event = ProgressEvent::new(phase, line);
+    event.package = package.map(|package| package.to_string());
+    event.percent = Some(percent);
+    return Some(event);
+}
+
+/// Parse the "--print" output of a dry run of an update
+fn parse_printed_update(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_printed("update", output, true);
+    if res.packages.is_empty() {
+        res.messages.push("No updates available".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parse the "--print" output of a dry run of an install
+fn parse_printed_install(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_printed("install", output, true);
+    if res.packages.is_empty() {
+        res.messages
+            .push("Package(s) already installed".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parse the "--print" output of a dry run of an uninstall
+fn parse_printed_uninstall(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_printed("uninstall", output, false);
+    if res.uninstalled_packages.is_empty() {
+        res.messages.push("No packages to uninstall".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parses the PRINT_FORMAT lines of the packages a transaction would change, e.g.
+/// "curl 8.7.1-1 core 1351213", removed packages are in the "local" repository
+fn parse_printed(subcommand: &'static str, output: &str, installing: bool) -> PackagesResponse {
+    let mut res = response(subcommand);
+    let mut download_byte_size: u64 = 0;
+    for line in output.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() != 4 {
+            continue;
+        }
+        let download_bytes = match installing {
+            true => parts[3].parse::<u64>().ok(),
+            false => None,
+        };
+        download_byte_size += download_bytes.unwrap_or_default();
+        let package = Package {
+            name: parts[0].to_string(),
+            version: Some(parts[1].to_string()),
+            repository: Some(parts[2].to_string()),
+            installed: installing,
+            download_bytes,
+            ..Default::default()
+        };
+        if installing {
+            res.packages.push(package);
+        } else {
+            res.uninstalled_packages.push(package);
+        }
+    }
+    if !res.packages.is_empty() {
+        res.packages_length = Some(res.packages.len());
+        res.packages_size_bytes = Some(download_byte_size);
+    }
+    if !res.uninstalled_packages.is_empty() {
+        res.uninstalled_packages_length = Some(res.uninstalled_packages.len());
+    }
+    return res;
+}
@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read};
+use std::sync::OnceLock;
+
+/// What a package manager is doing when it prints a line
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Refreshing,
+    Downloading,
+    Installing,
+    Updating,
+    Removing,
+    Configuring,
+    Verifying,
+    /// Any other line of output
+    Output,
+}
+
+/// Progress of a running package manager, sent for every line it prints with "--stream"
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProgressEvent {
+    pub phase: Phase,
+    /// Package the line is about, e.g. "curl"
+    pub package: Option<String>,
+    /// Size of the download the line is about
+    pub download_bytes: Option<u64>,
+    /// Progress of the whole phase, from counters like "(1/3)" or a percentage the package manager shows
+    pub percent: Option<u8>,
+    /// The line as printed by the package manager
+    pub line: String,
+}
+
+impl ProgressEvent {
+    pub fn new(phase: Phase, line: &str) -> ProgressEvent {
+        return ProgressEvent {
+            phase,
+            package: None,
+            download_bytes: None,
+            percent: None,
+            line: line.to_string(),
+        };
+    }
+}
+
+/// Gets the ProgressEvent of a line of output, or None if the line isn't known
+pub type ProgressParser = fn(&str) -> Option<ProgressEvent>;
+
+pub(super) type ProgressSink = Box<dyn Fn(&ProgressEvent) + Send + Sync>;
+
+static SINK: OnceLock<ProgressSink> = OnceLock::new();
+
+/// Sends the progress of every package manager run from now on to a sink, e.g. to print it.
+/// Without a sink, package managers run until they exit without reporting anything
+pub fn set_progress_sink(sink: impl Fn(&ProgressEvent) + Send + Sync + 'static) {
+    let _ = SINK.set(Box::new(sink));
+}
+
+pub(super) fn progress_sink() -> Option<&'static ProgressSink> {
+    return SINK.get();
+}
+
+/// Reads a stream of output until it ends, calling on_line for every line as soon as it is complete.
+/// Progress bars redraw their line after a "\r", so that ends a line as well
+pub(super) fn read_lines(
+    mut reader: impl Read,
+    mut on_line: impl FnMut(&str),
+) -> io::Result<Vec<u8>> {
+    let mut output = vec![];
+    let mut line = vec![];
+    let mut buffer = [0; 4096];
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        };
+        output.extend_from_slice(&buffer[..read]);
+        for &byte in &buffer[..read] {
+            if byte != b'\n' && byte != b'\r' {
+                line.push(byte);
+                continue;
+            }
+            if !line.is_empty() {
+                on_line(&String::from_utf8_lossy(&line));
+                line.clear();
+            }
+        }
+    }
+    if !line.is_empty() {
+        on_line(&String::from_utf8_lossy(&line));
+    }
+    return Ok(output);
+}
+
+/// Gets the percentage of a counter like "1/3", with or without brackets
+pub(super) fn counter_percent(counter: &str) -> Option<u8> {
+    let counter = counter.trim_matches(['(', ')', '[', ']']);
+    let (current, total) = counter.split_once('/')?;
+    let current: u64 = current.trim().parse().ok()?;
+    let total: u64 = total.trim().parse().ok()?;
+    if total == 0 || current > total {
+        return None;
+    }
+    return Some((current * 100 / total) as u8);
+}
+
+/// Gets a percentage like "45%" from the words of a line
+pub(super) fn shown_percent(line: &str) -> Option<u8> {
+    return line
+        .split_whitespace()
+        .find_map(|word| word.strip_suffix('%')?.parse::<u8>().ok())
+        .filter(|percent| *percent <= 100);
+}
@@ -0,0 +1,275 @@
+use super::*;
+use std::ops::Range;
+
+/// Gets the files with an extension in a directory of the configuration, sorted by name
+pub(super) fn config_files(dir: &str, extension: &str) -> Result<Vec<PathBuf>, PackagesError> {
+    let dir = etc_path(dir);
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(error) => return Err(SysadminError::spawn(&dir.to_string_lossy(), error)),
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+        .collect();
+    files.sort();
+    return Ok(files);
+}
+
+/// Reads the repositories of configuration files with a parser and sets the file of every repository
+pub(super) fn read_repos(
+    files: &[PathBuf],
+    parser: Parser,
+) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("repos");
+    for file in files {
+        let contents = read_file(file)?;
+        let mut parsed = parser(&contents)?;
+        for repo in parsed.repositories.iter_mut() {
+            repo.file = Some(file.to_string_lossy().to_string());
+        }
+        res.repositories.append(&mut parsed.repositories);
+        res.out.push_str(&contents);
+    }
+    return Ok(res);
+}
+
+/// Finds a repository by its id
+pub(super) fn find_repo(res: &PackagesResponse, id: &str) -> Result<Repository, PackagesError> {
+    return res
+        .repositories
+        .iter()
+        .find(|repo| repo.id == id)
+        .cloned()
+        .ok_or_else(|| SysadminError::NotFound {
+            message: format!("No repository with the id {}", id),
+        });
+}
+
+/// Fails if a repository with an id is configured already
+pub(super) fn check_new_id(res: &PackagesResponse, id: &str) -> Result<(), PackagesError> {
+    if res.repositories.iter().any(|repo| repo.id == id) {
+        return Err(SysadminError::UnsupportedOperation {
+            message: format!("A repository with the id {} exists already", id),
+        });
+    }
+    return Ok(());
+}
+
+/// Fails if a repository to add has suites or components, only apt repositories have them
+pub(super) fn check_no_suites(repo: &Repository) -> Result<(), PackagesError> {
+    if !repo.suites.is_empty() || !repo.components.is_empty() {
+        return Err(SysadminError::UnsupportedOperation {
+            message: "Only apt repositories have suites and components".to_string(),
+        });
+    }
+    return Ok(());
+}
+
+/// Gets the id of a repository to add, made from its first URL if it has none,
+/// e.g. "download-docker-com-linux-fedora" for "https://download.docker.com/linux/fedora"
+pub(super) fn new_id(repo: &Repository) -> String {
+    if !repo.id.is_empty() {
+        return repo.id.clone();
+    }
+    let url = repo
+        .urls
+        .first()
+        .map(|url| url.as_str())
+        .unwrap_or_default();
+    let url = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    return url
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase();
+}
+
+/// Response of a change to a repository, with the repository as it is configured after the change
+pub(super) fn changed_repo(
+    res: PackagesResponse,
+    id: &str,
+    message: String,
+) -> Result<PackagesResponse, PackagesError> {
+    let mut changed = response("repos");
+    changed.repositories = vec![find_repo(&res, id)?];
+    changed.out = message.clone();
+    changed.messages.push(message);
+    return Ok(changed);
+}
+
+/// Lists the repositories of the ".repo" files in directories of the configuration, used by dnf and zypper
+pub(super) fn ini_repos(dirs: &[&str]) -> Result<PackagesResponse, PackagesError> {
+    let mut files = vec![];
+    for dir in dirs {
+        files.append(&mut config_files(dir, "repo")?);
+    }
+    let mut res = read_repos(&files, parse_ini)?;
+    if res.repositories.is_empty() {
+        res.messages.push("No repositories configured".to_string());
+    }
+    return Ok(res);
+}
+
+/// Sets "enabled" in the section of a repository in its ".repo" file
+pub(super) fn ini_enable(
+    dirs: &[&str],
+    id: &str,
+    enabled: bool,
+) -> Result<PackagesResponse, PackagesError> {
+    let repo = find_repo(&ini_repos(dirs)?, id)?;
+    let path = PathBuf::from(repo.file.unwrap_or_default());
+    let contents = ini_set(
+        &read_file(&path)?,
+        id,
+        "enabled",
+        if enabled { "1" } else { "0" },
+    );
+    write_file(&path, &contents)?;
+    let message = match enabled {
+        true => format!("Enabled repository {}", id),
+        false => format!("Disabled repository {}", id),
+    };
+    return changed_repo(ini_repos(dirs)?, id, message);
+}
+
+/// Writes a repository to a new ".repo" file in the first directory, named after its id
+pub(super) fn ini_add(dirs: &[&str], repo: &Repository) -> Result<PackagesResponse, PackagesError> {
+    let id = new_id(repo);
+    check_new_id(&ini_repos(dirs)?, &id)?;
+    check_no_suites(repo)?;
+    let mut contents = format!("[{}]\n", id);
+    contents.push_str(&format!("name={}\n", repo.name.as_deref().unwrap_or(&id)));
+    contents.push_str(&format!("baseurl={}\n", repo.urls.join(" ")));
+    contents.push_str(&format!("enabled={}\n", repo.enabled as u8));
+    if let Some(gpgcheck) = repo.gpgcheck {
+        contents.push_str(&format!("gpgcheck={}\n", gpgcheck as u8));
+    }
+    if let Some(priority) = repo.priority {
+        contents.push_str(&format!("priority={}\n", priority));
+    }
+    write_file(&etc_path(dirs[0]).join(format!("{}.repo", id)), &contents)?;
+    return changed_repo(ini_repos(dirs)?, &id, format!("Added repository {}", id));
+}
+
+/// Removes the section of a repository from its ".repo" file, and the file if nothing else is in it
+pub(super) fn ini_remove(dirs: &[&str], id: &str) -> Result<PackagesResponse, PackagesError> {
+    let res = ini_repos(dirs)?;
+    let repo = find_repo(&res, id)?;
+    let path = PathBuf::from(repo.file.clone().unwrap_or_default());
+    let contents = read_file(&path)?;
+    let mut lines: Vec<&str> = contents.lines().collect();
+    if let Some(section) = section_lines(&lines, id) {
+        lines.drain(section);
+    }
+    let mut contents = lines.join("\n") + "\n";
+    if !lines.iter().any(|line| section_header(line).is_some()) {
+        contents.clear();
+    }
+    write_file(&path, &contents)?;
+    let mut removed = changed_repo(res, id, format!("Removed repository {}", id))?;
+    removed.repositories[0].file = None;
+    return Ok(removed);
+}
+
+/// Gets the id of a section header like "[fedora]"
+fn section_header(line: &str) -> Option<&str> {
+    return line.trim().strip_prefix('[')?.strip_suffix(']');
+}
+
+/// Gets the lines of the section of a repository, from its header to the next header
+fn section_lines(lines: &[&str], id: &str) -> Option<Range<usize>> {
+    let header = lines
+        .iter()
+        .position(|line| section_header(line) == Some(id))?;
+    let end = lines[header + 1..]
+        .iter()
+        .position(|line| section_header(line).is_some())
+        .map(|length| header + 1 + length)
+        .unwrap_or(lines.len());
+    return Some(header..end);
+}
+
+/// Sets a key in the section of a repository, adding it below the header if the section doesn't have it
+fn ini_set(contents: &str, id: &str, key: &str, value: &str) -> String {
+    let mut lines: Vec<&str> = contents.lines().collect();
+    let Some(section) = section_lines(&lines, id) else {
+        return contents.to_string();
+    };
+    let setting = format!("{}={}", key, value);
+    let current = lines[section.clone()].iter().position(|line| {
+        line.split_once('=')
+            .is_some_and(|(current, _)| current.trim() == key)
+    });
+    match current {
+        Some(line) => lines[section.start + line] = &setting,
+        None => lines.insert(section.start + 1, &setting),
+    }
+    return lines.join("\n") + "\n";
+}
+
+/// Parse a ".repo" file of dnf or zypper, e.g.
+///
+/// [fedora]
+/// name=Fedora $releasever - $basearch
+/// metalink=https://mirrors.fedoraproject.org/metalink?repo=fedora-$releasever&arch=$basearch
+/// enabled=1
+/// gpgcheck=1
+pub(super) fn parse_ini(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("repos");
+    let mut key = "";
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') {
+            continue;
+        }
+        if let Some(id) = section_header(trimmed) {
+            res.repositories.push(Repository {
+                id: id.to_string(),
+                name: None,
+                urls: vec![],
+                enabled: true,
+                gpgcheck: None,
+                priority: None,
+                suites: vec![],
+                components: vec![],
+                file: None,
+            });
+            continue;
+        }
+        let Some(repo) = res.repositories.last_mut() else {
+            return Err(SysadminError::parse(line, "expected a section"));
+        };
+        // Further base URLs can follow on indented lines
+        let value = match trimmed.split_once('=') {
+            Some((current, value)) if !line.starts_with(char::is_whitespace) => {
+                key = current.trim();
+                value.trim()
+            }
+            _ if key == "baseurl" => trimmed,
+            _ => continue,
+        };
+        match key {
+            "name" => repo.name = Some(value.to_string()),
+            "baseurl" => repo.urls.extend(
+                value
+                    .split([' ', ','])
+                    .filter(|url| !url.is_empty())
+                    .map(|url| url.to_string()),
+            ),
+            "mirrorlist" | "metalink" => repo.urls.push(value.to_string()),
+            "enabled" => repo.enabled = ini_bool(value),
+            "gpgcheck" => repo.gpgcheck = Some(ini_bool(value)),
+            "priority" => repo.priority = value.parse().ok(),
+            _ => {}
+        }
+    }
+    return Ok(res);
+}
+
+fn ini_bool(value: &str) -> bool {
+    return matches!(value.to_lowercase().as_str(), "1" | "yes" | "true" | "on");
+}
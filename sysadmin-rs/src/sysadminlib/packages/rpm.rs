@@ -0,0 +1,145 @@
+use super::*;
+
+/// Queryformat of an installed package, rpm expands the escapes itself
+const PACKAGE_FORMAT: &str = r"%{NAME}\t%{ARCH}\t%{EPOCHNUM}\t%{VERSION}\t%{RELEASE}\n";
+
+/// Gets the installed packages that own a file from the rpm database, used by dnf and zypper
+pub(super) fn owner(path: &str) -> Result<PackagesResponse, PackagesError> {
+    let output = run_query(
+        native_command("rpm")
+            .args(["-qf", "--queryformat", PACKAGE_FORMAT])
+            .arg(path),
+        &["is not owned by any package", "No such file or directory"],
+    )?;
+    let mut res = parse_output(output, |output| parse_packages("owner", output))?;
+    for package in res.packages.iter_mut() {
+        package.files = vec![path.to_string()];
+    }
+    return Ok(res);
+}
+
+/// Gets an installed package with its files from the rpm database, used by dnf and zypper.
+/// Every installed arch of the package is a package of its own
+pub(super) fn files(package: &str) -> Result<PackagesResponse, PackagesError> {
+    let output = run_query(
+        native_command("rpm")
+            .args(["-q", "--queryformat"])
+            .arg(format!(r"{}[%{{FILENAMES}}\n]", PACKAGE_FORMAT))
+            .arg(package),
+        &["is not installed"],
+    )?;
+    return parse_output(output, |output| parse_packages("files", output));
+}
+
+/// Verifies the installed files of packages against the rpm database, used by dnf and zypper
+pub(super) fn verify(packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+    return verify_packages("rpm", &["-Va"], &["-V"], packages, parse_verify);
+}
+
+/// Gets the parser for the output of a subcommand, used by the fixture tests
+#[cfg(test)]
+pub(super) fn parser(subcommand: &str) -> Option<Parser> {
+    return match subcommand {
+        "owner" => Some(|output| parse_packages("owner", output)),
+        "files" => Some(|output| parse_packages("files", output)),
+        "verify" => Some(parse_verify),
+        _ => None,
+    };
+}
+
+/// Parse the tab separated packages of PACKAGE_FORMAT, each followed by its files if they were queried, e.g.
+///
+/// curl x86_64 0 8.6.0 4.fc40
+/// /usr/bin/curl
+/// /usr/share/man/man1/curl.1.gz
+fn parse_packages(
+    subcommand: &'static str,
+    output: &str,
+) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response(subcommand);
+    for line in output.lines() {
+        if line.starts_with('/') {
+            let Some(package) = res.packages.last_mut() else {
+                return Err(SysadminError::parse(line, "expected a package"));
+            };
+            package.files.push(line.to_string());
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 5 {
+            return Err(SysadminError::parse(line, "expected 5 fields"));
+        }
+        let version = match fields[2] {
+            "0" => format!("{}-{}", fields[3], fields[4]),
+            epoch => format!("{}:{}-{}", epoch, fields[3], fields[4]),
+        };
+        res.packages.push(Package {
+            name: fields[0].to_string(),
+            arch: fields[1].to_string(),
+            version: Some(version),
+            installed: true,
+            ..Default::default()
+        });
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Parse the output of "rpm -V", also the one of "dpkg --verify", a line for every file that differs, e.g.
+///
+/// S.5....T.  c /etc/ssh/sshd_config
+/// missing     /usr/bin/curl
+///
+/// A letter tells every test that failed, "." that it passed and "?" that it couldn't be done.
+/// The letter after the tests marks the kind of file, "c" for configuration files
+pub(super) fn parse_verify(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("verify");
+    for line in output.lines() {
+        if let Some(package) = line.strip_prefix("package ") {
+            if package.ends_with(" is not installed") {
+                return Err(SysadminError::NotFound {
+                    message: format!("Package {}", package),
+                });
+            }
+        }
+        let Some((tests, rest)) = line.split_once(' ') else {
+            continue;
+        };
+        let rest = rest.trim_start();
+        let (kind, path) = match rest.split_once(' ') {
+            Some((kind, path)) if kind.len() == 1 => (kind, path.trim_start()),
+            _ => ("", rest),
+        };
+        if !path.starts_with('/') {
+            continue;
+        }
+        let problems = match tests {
+            "missing" => vec!["missing".to_string()],
+            _ if tests.len() == 8 || tests.len() == 9 => tests
+                .chars()
+                .filter_map(|test| match test {
+                    'S' => Some("size"),
+                    'M' => Some("mode"),
+                    '5' => Some("checksum"),
+                    'D' => Some("device"),
+                    'L' => Some("link"),
+                    'U' => Some("owner"),
+                    'G' => Some("group"),
+                    'T' => Some("mtime"),
+                    'P' => Some("capabilities"),
+                    _ => None,
+                })
+                .map(|problem| problem.to_string())
+                .collect(),
+            // Other lines are messages like "Unsatisfied dependencies for curl"
+            _ => continue,
+        };
+        res.findings.push(Finding {
+            path: path.to_string(),
+            package: None,
+            config: kind == "c",
+            problems,
+        });
+    }
+    return Ok(res);
+}
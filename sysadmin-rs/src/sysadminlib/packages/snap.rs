@@ -0,0 +1,389 @@
+use super::*;
+
+pub struct Snap;
+
+pub static SNAP: Snap = Snap;
+
+impl PackageBackend for Snap {
+    fn manager(&self) -> &'static str {
+        return "snap";
+    }
+
+    fn list(
+        &self,
+        _installed: bool,
+        available: bool,
+        extras: bool,
+        packages: &[String],
+    ) -> Result<PackagesResponse, PackagesError> {
+        // snap can only list installed snaps, the store is only searchable
+        if available || extras {
+            return Err(unsupported(
+                self.manager(),
+                "list available or extra packages",
+            ));
+        }
+        let output = run_command(native_command("snap").arg("list").args(packages))?;
+        return parse_output(output, parse_list);
+    }
+
+    fn info(&self, packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(
+            native_command("snap")
+                .args(["info", "--verbose"])
+                .args(packages),
+        )?;
+        return parse_output(output, parse_info);
+    }
+
+    fn search(&self, search_terms: &[String]) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(native_command("snap").arg("find").args(search_terms))?;
+        return parse_output(output, parse_search);
+    }
+
+    fn refresh(&self) -> Result<PackagesResponse, PackagesError> {
+        // The store is always queried directly, so there is nothing to refresh.
+        // The pending updates are listed instead
+        let output = run_command(native_command("snap").args(["refresh", "--list"]))?;
+        return parse_output(output, parse_refresh);
+    }
+
+    fn update(&self, fake: bool, dry_run: bool) -> Result<PackagesResponse, PackagesError> {
+        // "snap refresh" can't stop after downloading
+        if fake {
+            return Err(unsupported(self.manager(), "only download updates"));
+        }
+        if dry_run {
+            let output = run_command(native_command("snap").args(["refresh", "--list"]))?;
+            return parse_output(output, parse_pending_update);
+        }
+        let output = run_command(native_command("snap").arg("refresh"))?;
+        return parse_output(output, parse_update);
+    }
+
+    fn install(
+        &self,
+        packages: &[String],
+        dry_run: bool,
+    ) -> Result<PackagesResponse, PackagesError> {
+        if dry_run {
+            return Err(unsupported(self.manager(), "install packages as a dry run"));
+        }
+        let output = run_command(native_command("snap").arg("install").args(packages))?;
+        return parse_output(output, parse_install);
+    }
+
+    fn uninstall(
+        &self,
+        packages: &[String],
+        dry_run: bool,
+    ) -> Result<PackagesResponse, PackagesError> {
+        if dry_run {
+            return Err(unsupported(
+                self.manager(),
+                "uninstall packages as a dry run",
+            ));
+        }
+        let output = run_command(native_command("snap").arg("remove").args(packages))?;
+        return parse_output(output, parse_uninstall);
+    }
+}
+
+/// Gets the parser for the output of a subcommand, used by the fixture tests
+#[cfg(test)]
+pub(super) fn parser(subcommand: &str) -> Option<Parser> {
+    return match subcommand {
+        "list" => Some(parse_list),
+        "info" => Some(parse_info),
+        "search" => Some(parse_search),
+        "refresh" => Some(parse_refresh),
+        "update" => Some(parse_update),
+        "install" => Some(parse_install),
+        "uninstall" => Some(parse_uninstall),
+        _ => None,
+    };
+}
+
+/// Splits a table row into whitespace separated columns, the last one keeps its spaces
+fn columns(line: &str, count: usize) -> Vec<&str> {
+    let mut columns = vec![];
+    let mut rest = line.trim();
+    while columns.len() + 1 < count {
+        let Some((column, remaining)) = rest.split_once(char::is_whitespace) else {
+            break;
+        };
+        columns.push(column);
+        rest = remaining.trim_start();
+    }
+    columns.push(rest);
+    return columns;
+}
+
+/// Gets the confinement from the notes of a snap, e.g. "classic" or "-"
+fn confinement(notes: &str) -> String {
+    for note in notes.split(',') {
+        if note == "classic" || note == "devmode" {
+            return note.to_string();
+        }
+    }
+    return "strict".to_string();
+}
+
+fn optional(value: &str) -> Option<String> {
+    return match value.trim() {
+        "" | "-" => None,
+        value => Some(value.to_string()),
+    };
+}
+
+/// Parse list output
+fn parse_list(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("list");
+    for line in output.lines().skip(1) {
+        // e.g. "firefox    124.0.2-1    4173   latest/stable    mozilla✓      -"
+        let fields = columns(line, 6);
+        if fields.len() != 6 {
+            continue;
+        }
+        res.packages.push(Package {
+            name: fields[0].to_string(),
+            version: Some(fields[1].to_string()),
+            installed: true,
+            channel: optional(fields[3]),
+            confinement: Some(confinement(fields[5])),
+            ..Default::default()
+        });
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Parse info output, "key: value" lines with indented blocks for e.g. the channels and notes.
+/// Every snap starts with its "name:" line
+fn parse_info(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("info");
+    let mut block = "";
+    for line in output.lines() {
+        if !line.starts_with(' ') {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            block = key;
+            let value = value.trim();
+            if key == "name" {
+                res.packages.push(Package {
+                    name: value.to_string(),
+                    ..Default::default()
+                });
+                continue;
+            }
+            let Some(package) = res.packages.last_mut() else {
+                continue;
+            };
+            match key {
+                "summary" => package.description = optional(value),
+                "store-url" => package.url = optional(value),
+                "license" if value != "unset" => package.license = optional(value),
+                "tracking" => package.channel = optional(value),
+                // e.g. "installed:          124.0.2-1             (4173) 283MB -"
+                "installed" => {
+                    let fields: Vec<&str> = value.split_whitespace().collect();
+                    package.installed = true;
+                    package.version = fields.first().map(|version| version.to_string());
+                    package.size_bytes = fields.get(2).and_then(|size| parse_byte_size(size));
+                    if let Some(notes) = fields.get(3) {
+                        package.confinement.get_or_insert(confinement(notes));
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        let Some(package) = res.packages.last_mut() else {
+            continue;
+        };
+        let Some((key, value)) = line.trim().split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match block {
+            // e.g. "  confinement: strict"
+            "notes" if key == "confinement" => package.confinement = optional(value),
+            // e.g. "  latest/stable:    124.0.2-1  2024-04-09 (4173) 283MB -", the tracked channel
+            // or the first one for snaps that aren't installed
+            "channels"
+                if package
+                    .channel
+                    .as_deref()
+                    .is_none_or(|channel| channel == key) =>
+            {
+                let fields: Vec<&str> = value.split_whitespace().collect();
+                if fields.len() >= 4 && package.version.is_none() {
+                    package.version = Some(fields[0].to_string());
+                    package.download_bytes = parse_byte_size(fields[3]);
+                    package.channel = Some(key.to_string());
+                    package
+                        .confinement
+                        .get_or_insert(confinement(fields.get(4).unwrap_or(&"-")));
+                }
+            }
+            _ => {}
+        }
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Parse search output
+fn parse_search(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("search");
+    for line in output.lines().skip(1) {
+        // e.g. "gimp     2.10.38    snapcrafters✪   -      GNU Image Manipulation Program"
+        let fields = columns(line, 5);
+        if fields.len() != 5 {
+            continue;
+        }
+        res.packages.push(Package {
+            name: fields[0].to_string(),
+            version: Some(fields[1].to_string()),
+            description: optional(fields[4]),
+            confinement: Some(confinement(fields[3])),
+            ..Default::default()
+        });
+    }
+    if res.packages.is_empty() {
+        res.messages.push("Nothing matched search".to_string());
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Parse refresh output, the pending updates of "snap refresh --list"
+fn parse_refresh(output: &str) -> Result<PackagesResponse, PackagesError> {
+    return Ok(parse_pending("refresh", output));
+}
+
+/// Parse the output of "snap refresh --list" for a dry run update
+fn parse_pending_update(output: &str) -> Result<PackagesResponse, PackagesError> {
+    return Ok(parse_pending("update", output));
+}
+
+fn parse_pending(subcommand: &'static str, output: &str) -> PackagesResponse {
+    let mut res = response(subcommand);
+    for line in output.lines().skip(1) {
+        // e.g. "firefox  125.0-2  4207  284MB  mozilla✓  -"
+        let fields = columns(line, 6);
+        if fields.len() != 6 {
+            continue;
+        }
+        res.packages.push(Package {
+            name: fields[0].to_string(),
+            version: Some(fields[1].to_string()),
+            installed: true,
+            download_bytes: parse_byte_size(fields[3]),
+            confinement: Some(confinement(fields[5])),
+            ..Default::default()
+        });
+    }
+    match res.packages.len() {
+        // "All snaps up to date." is printed to stderr
+        0 => res.messages.push("No updates available".to_string()),
+        count => {
+            res.messages.push(format!("{} update(s) available", count));
+            res.packages_length = Some(count);
+            res.packages_size_bytes = res.packages.iter().map(|p| p.download_bytes).sum();
+        }
+    }
+    return res;
+}
+
+/// Parse update output
+fn parse_update(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_transaction("update", output);
+    if res.packages.is_empty() {
+        res.messages.push("No updates available".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parse install output
+fn parse_install(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_transaction("install", output);
+    if res.packages.is_empty() {
+        res.messages
+            .push("Package(s) already installed".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parse uninstall output
+fn parse_uninstall(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_transaction("uninstall", output);
+    if res.uninstalled_packages.is_empty() {
+        res.messages.push("No packages to uninstall".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parses the line snap prints for every changed snap, e.g. "firefox 125.0-2 from Mozilla✓ refreshed",
+/// "code (stable) 1.88.1 from Visual Studio Code (vscode✓) installed" or "firefox removed"
+fn parse_transaction(subcommand: &'static str, output: &str) -> PackagesResponse {
+    let mut res = response(subcommand);
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(name) = line
+            .strip_suffix(" removed")
+            .or(line.strip_suffix(" removed (snap data snapshot saved)"))
+        {
+            res.uninstalled_packages.push(Package {
+                name: name.to_string(),
+                ..Default::default()
+            });
+            continue;
+        }
+        let Some(snap) = line
+            .strip_suffix(" installed")
+            .or(line.strip_suffix(" refreshed"))
+        else {
+            continue;
+        };
+        let Some((snap, _publisher)) = snap.split_once(" from ") else {
+            continue;
+        };
+        let fields: Vec<&str> = snap.split_whitespace().collect();
+        let (channel, version) = match fields.as_slice() {
+            [_, channel, version] => (Some(channel.trim_matches(['(', ')']).to_string()), version),
+            [_, version] => (None, version),
+            _ => continue,
+        };
+        res.packages.push(Package {
+            name: fields[0].to_string(),
+            version: Some(version.to_string()),
+            installed: true,
+            channel,
+            ..Default::default()
+        });
+    }
+    if !res.packages.is_empty() {
+        res.packages_length = Some(res.packages.len());
+    }
+    if !res.uninstalled_packages.is_empty() {
+        res.uninstalled_packages_length = Some(res.uninstalled_packages.len());
+    }
+    return res;
+}
+
+/// Parses a line snap prints while it runs, snap only prints a line for every changed snap
+/// like the ones of parse_transaction
+pub(super) fn parse_progress(line: &str) -> Option<ProgressEvent> {
+    let name = line.split_whitespace().next()?;
+    let phase = match line.trim().rsplit(' ').next()? {
+        "installed" => Phase::Installing,
+        "refreshed" => Phase::Updating,
+        "removed" | "saved)" => Phase::Removing,
+        _ => return None,
+    };
+    let mut event = ProgressEvent::new(phase, line);
+    event.package = Some(name.to_string());
+    return Some(event);
+}
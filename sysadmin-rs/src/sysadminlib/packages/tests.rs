@@ -0,0 +1,172 @@
+//! Parser tests against captured package manager output.
+//!
+//! Every "tests/fixtures/<manager>/<subcommand>/<name>.txt" is parsed with the parser of that
+//! subcommand and compared to the PackagesResponse in "<name>.json" next to it.
+//! Run with UPDATE_SNAPSHOTS=1 to write the snapshots from the current parsers.
+
+use super::*;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+fn check_fixtures(manager: &str, parser: fn(&str) -> Option<Parser>) {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(manager);
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+    let mut checked = 0;
+    let mut failures: Vec<String> = vec![];
+
+    let mut subcommands: Vec<_> = fs::read_dir(&root)
+        .unwrap_or_else(|_| panic!("missing fixtures for {}", manager))
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    subcommands.sort();
+    for dir in subcommands {
+        let subcommand = dir.file_name().unwrap().to_string_lossy().to_string();
+        let parser = parser(&subcommand)
+            .unwrap_or_else(|| panic!("{} has no parser for {}", manager, subcommand));
+
+        let mut fixtures: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+            .collect();
+        fixtures.sort();
+        for fixture in fixtures {
+            let output = fs::read_to_string(&fixture).unwrap();
+            // Failing fixtures are compared by their error message
+            let actual = match parser(&output) {
+                Ok(mut res) => {
+                    res.humanize_sizes();
+                    serde_json::to_value(&res).unwrap()
+                }
+                Err(error) => json!({ "error": error.to_string() }),
+            };
+            let snapshot = fixture.with_extension("json");
+            if update {
+                let mut contents = serde_json::to_string_pretty(&actual).unwrap();
+                contents.push('\n');
+                fs::write(&snapshot, contents).unwrap();
+            } else {
+                let expected: Value = fs::read_to_string(&snapshot)
+                    .map(|contents| serde_json::from_str(&contents).unwrap())
+                    .unwrap_or(Value::Null);
+                if actual != expected {
+                    failures.push(format!(
+                        "{}\n{}",
+                        fixture.display(),
+                        serde_json::to_string_pretty(&actual).unwrap()
+                    ));
+                }
+            }
+            checked += 1;
+        }
+    }
+
+    assert!(checked > 0, "no fixtures for {}", manager);
+    assert!(
+        failures.is_empty(),
+        "output didn't match the snapshot for:\n{}",
+        failures.join("\n\n")
+    );
+}
+
+#[test]
+fn apk_fixtures() {
+    check_fixtures("apk", apk::parser);
+}
+
+#[test]
+fn apt_fixtures() {
+    check_fixtures("apt", apt::parser);
+}
+
+#[test]
+fn dnf_fixtures() {
+    check_fixtures("dnf", dnf::parser);
+}
+
+#[test]
+fn flatpak_fixtures() {
+    check_fixtures("flatpak", flatpak::parser);
+}
+
+#[test]
+fn pacman_fixtures() {
+    check_fixtures("pacman", pacman::parser);
+}
+
+#[test]
+fn rpm_fixtures() {
+    check_fixtures("rpm", rpm::parser);
+}
+
+#[test]
+fn snap_fixtures() {
+    check_fixtures("snap", snap::parser);
+}
+
+#[test]
+fn zypper_fixtures() {
+    check_fixtures("zypper", zypper::parser);
+}
+
+#[test]
+fn byte_sizes() {
+    assert_eq!(parse_byte_size("1,446 kB"), Some(1_446_000));
+    assert_eq!(parse_byte_size("9.20 MiB"), Some(9_646_899));
+    assert_eq!(parse_byte_size("2 TiB"), Some(2_199_023_255_552));
+    assert_eq!(parse_byte_size("1.5 GB"), Some(1_500_000_000));
+    assert_eq!(parse_byte_size("64 k"), Some(65_536));
+    assert_eq!(parse_byte_size("1.2M"), Some(1_258_291));
+    assert_eq!(parse_byte_size("622592"), Some(622_592));
+    assert_eq!(parse_byte_size("12 furlongs"), None);
+    assert_eq!(parse_byte_size(""), None);
+    assert_eq!(simplify_byte_size(1023), "1023.00 B");
+    assert_eq!(simplify_byte_size(1024), "1.00 KiB");
+    assert_eq!(simplify_byte_size(2_199_023_255_552), "2.00 TiB");
+}
+
+#[test]
+fn unix_times() {
+    assert_eq!(format_unix_time(0), "1970-01-01 00:00:00");
+    assert_eq!(format_unix_time(951_825_600), "2000-02-29 12:00:00");
+    assert_eq!(format_unix_time(1_711_965_600), "2024-04-01 10:00:00");
+    assert_eq!(format_unix_time(-1), "1969-12-31 23:59:59");
+}
+
+#[test]
+fn progress_lines() {
+    // The parser, a line and the phase, package, percent and download size expected for it
+    type Case = (
+        ProgressParser,
+        &'static str,
+        Phase,
+        Option<&'static str>,
+        Option<u8>,
+        Option<u64>,
+    );
+    let cases: [Case; 12] = [
+        (apk::parse_progress, "(2/5) Installing brotli-libs (1.1.0-r1)", Phase::Installing, Some("brotli-libs"), Some(40), None),
+        (apt::parse_progress, "Get:2 http://deb.debian.org/debian bookworm/main amd64 ripgrep amd64 13.0.0-4+b2 [1,446 kB]", Phase::Downloading, Some("ripgrep"), None, Some(1_446_000)),
+        (apt::parse_progress, "Get:1 http://deb.debian.org/debian bookworm InRelease [151 kB]", Phase::Refreshing, None, None, Some(151_000)),
+        (apt::parse_progress, "Unpacking curl (8.6.0-1) over (8.5.0-2) ...", Phase::Updating, Some("curl"), None, None),
+        (apt::parse_progress, "Setting up libpcre2-8-0:amd64 (10.42-1) ...", Phase::Configuring, Some("libpcre2-8-0"), None, None),
+        (dnf::parse_progress, "[1/3] curl-0:8.6.0-4.fc40.x86_64         100% |   1.0 MiB/s | 301.7 KiB |  00m00s", Phase::Downloading, Some("curl-0:8.6.0-4.fc40.x86_64"), Some(33), Some(308_941)),
+        (dnf::parse_progress, "(2/4): libcurl-8.6.0-4.fc39.x86_64.rpm  345 kB/s | 345 kB  00:01", Phase::Downloading, Some("libcurl-8.6.0-4.fc39.x86_64"), Some(50), Some(345_000)),
+        (dnf::parse_progress, "  Upgrading        : curl-8.6.0-4.fc39.x86_64     3/4", Phase::Updating, Some("curl-8.6.0-4.fc39.x86_64"), Some(75), None),
+        (flatpak::parse_progress, "Installing app/org.gnome.Calculator/x86_64/stable", Phase::Installing, Some("org.gnome.Calculator"), None, None),
+        (pacman::parse_progress, "(1/3) upgrading curl                                [######################] 100%", Phase::Updating, Some("curl"), Some(33), None),
+        (snap::parse_progress, "gimp removed (snap data snapshot saved)", Phase::Removing, Some("gimp"), None, None),
+        (zypper::parse_progress, "<message type=\"info\">Retrieving: ripgrep-14.1.0-1.2.x86_64 (repo-oss) (1/2), 1.5 MiB</message>", Phase::Downloading, Some("ripgrep-14.1.0-1.2.x86_64"), Some(50), Some(1_572_864)),
+    ];
+    for (parser, line, phase, package, percent, download_bytes) in cases {
+        let event = parser(line).unwrap_or_else(|| panic!("no progress for {}", line));
+        assert_eq!(event.phase, phase, "{}", line);
+        assert_eq!(event.package.as_deref(), package, "{}", line);
+        assert_eq!(event.percent, percent, "{}", line);
+        assert_eq!(event.download_bytes, download_bytes, "{}", line);
+    }
+    assert!(apt::parse_progress("Reading package lists...").is_none());
+}
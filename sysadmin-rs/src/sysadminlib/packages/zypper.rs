@@ -1,65 +1,782 @@
 use super::*;
+use roxmltree::{Document, Node};
+use std::collections::HashMap;
 
-pub static ZYPPER: PackageManager = PackageManager {
-    manager: "dnf",
-    list: ListArgs {
-        command: &["list"],
-        installed: "--installed-only",
-        available: "--not-installed-only",
-        extras: "",
-        parser: parse_list,
-    },
-    info: InfoArgs {
-        command: &["info"],
-        parser: parse_info,
-    },
-    search: SearchArgs {
-        command: &["search"],
-        parser: parse_search,
-    },
-    refresh_data: RefreshArgs {
-        command: &["refresh"],
-        parser: parse_refresh,
-    },
-    update: UpdateArgs {
-        commands: &[&["refresh"], &["update"]],
-        fake: "",
-        parser: parse_update,
-    },
-    install: InstallArgs {
-        command: &["install"],
-        parser: parse_install,
-    },
-    uninstall: UninstallArgs {
-        command: &["remove"],
-        parser: parse_uninstall,
-    },
-};
-
-fn parse_list(output: &String) -> PackagesResponse {
-    todo!();
-}
-
-fn parse_info(output: &String) -> PackagesResponse {
-    todo!();
-}
-
-fn parse_search(output: &String) -> PackagesResponse {
-    todo!();
-}
-
-fn parse_refresh(output: &String) -> PackagesResponse {
-    todo!();
-}
-
-fn parse_update(output: &String) -> PackagesResponse {
-    todo!();
-}
-
-fn parse_install(output: &String) -> PackagesResponse {
-    todo!();
-}
-
-fn parse_uninstall(output: &String) -> PackagesResponse {
-    todo!();
+pub struct Zypper;
+
+pub static ZYPPER: Zypper = Zypper;
+
+/// Directories of the ".repo" files, new repositories are added to the first one
+const REPO_DIRS: [&str; 1] = ["zypp/repos.d"];
+
+impl PackageBackend for Zypper {
+    fn manager(&self) -> &'static str {
+        return "zypper";
+    }
+
+    fn list(
+        &self,
+        installed: bool,
+        available: bool,
+        extras: bool,
+        packages: &[String],
+    ) -> Result<PackagesResponse, PackagesError> {
+        let mut res = response("list");
+        if installed || available || !extras {
+            let mut command = native_command("zypper");
+            command.args(["--xmlout", "search", "--details", "--type", "package"]);
+            // Without either flag zypper searches installed and available packages
+            if installed && !available {
+                command.arg("--installed-only");
+            }
+            if available && !installed {
+                command.arg("--not-installed-only");
+            }
+            let output = run_command(command.args(packages))?;
+            let mut found = parse_output(output, parse_list)?;
+            res.packages.append(&mut found.packages);
+            res.out.push_str(&found.out);
+        }
+        // The extra packages are the orphaned ones no repository provides, they are installed
+        if extras && !installed {
+            let output = run_command(native_command("zypper").args([
+                "--non-interactive",
+                "packages",
+                "--orphaned",
+            ]))?;
+            let mut orphaned = parse_output(output, |output| parse_package_table("list", output))?;
+            orphaned
+                .packages
+                .retain(|package| packages.is_empty() || packages.contains(&package.name));
+            res.packages.append(&mut orphaned.packages);
+            res.out.push_str(&orphaned.out);
+        }
+        res.packages_length = Some(res.packages.len());
+        return Ok(res);
+    }
+
+    fn info(&self, packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(
+            native_command("zypper")
+                .args(["--xmlout", "info"])
+                .args(packages),
+        )?;
+        return parse_output(output, parse_info);
+    }
+
+    fn search(&self, search_terms: &[String]) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(
+            native_command("zypper")
+                .args(["--xmlout", "search", "--type", "package"])
+                .args(search_terms),
+        )?;
+        return parse_output(output, parse_search);
+    }
+
+    fn refresh(&self) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(native_command("zypper").args(["--xmlout", "refresh"]))?;
+        return parse_output(output, parse_refresh);
+    }
+
+    fn update(&self, fake: bool, dry_run: bool) -> Result<PackagesResponse, PackagesError> {
+        if dry_run {
+            let output = run_command(&mut transaction("update", true))?;
+            return parse_output(output, parse_update);
+        }
+        run_command(native_command("zypper").args(["--xmlout", "refresh"]))?;
+        let mut command = transaction("update", false);
+        if fake {
+            command.arg("--download-only");
+        }
+        let output = run_command(&mut command)?;
+        let mut res = parse_output(output, parse_update)?;
+        res.messages.insert(0, "Updated repos".to_string());
+        return Ok(res);
+    }
+
+    fn install(
+        &self,
+        packages: &[String],
+        dry_run: bool,
+    ) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(transaction("install", dry_run).args(packages))?;
+        return parse_output(output, parse_install);
+    }
+
+    fn uninstall(
+        &self,
+        packages: &[String],
+        dry_run: bool,
+    ) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(transaction("remove", dry_run).args(packages))?;
+        return parse_output(output, parse_uninstall);
+    }
+
+    fn history(&self, limit: usize) -> Result<PackagesResponse, PackagesError> {
+        let output = read_log("zypp/history")?;
+        let mut res = parse_output(output, parse_history)?;
+        res.transactions.truncate(limit);
+        return Ok(res);
+    }
+
+    fn repos(&self) -> Result<PackagesResponse, PackagesError> {
+        return repos::ini_repos(&REPO_DIRS);
+    }
+
+    fn enable_repo(&self, id: &str, enabled: bool) -> Result<PackagesResponse, PackagesError> {
+        return repos::ini_enable(&REPO_DIRS, id, enabled);
+    }
+
+    fn add_repo(&self, repo: &Repository) -> Result<PackagesResponse, PackagesError> {
+        return repos::ini_add(&REPO_DIRS, repo);
+    }
+
+    fn remove_repo(&self, id: &str) -> Result<PackagesResponse, PackagesError> {
+        return repos::ini_remove(&REPO_DIRS, id);
+    }
+
+    fn hold(&self, packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(
+            native_command("zypper")
+                .args(["--non-interactive", "addlock"])
+                .args(packages),
+        )?;
+        return held(self.holds()?, "hold", packages, output);
+    }
+
+    fn unhold(&self, packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(
+            native_command("zypper")
+                .args(["--non-interactive", "removelock"])
+                .args(packages),
+        )?;
+        return held(self.holds()?, "unhold", packages, output);
+    }
+
+    fn advisories(&self) -> Result<PackagesResponse, PackagesError> {
+        let output = run_command(native_command("zypper").args(["--xmlout", "list-patches"]))?;
+        let mut res = parse_output(output, parse_patches)?;
+        if res.advisories.is_empty() {
+            return Ok(res);
+        }
+        // The XML of the patches doesn't have their packages, "info" shows them as conflicts
+        let info = run_command(
+            native_command("zypper")
+                .args(["info", "-t", "patch"])
+                .args(res.advisories.iter().map(|advisory| &advisory.id)),
+        )?;
+        for patch in parse_patch_info(&info)?.advisories {
+            if let Some(advisory) = res
+                .advisories
+                .iter_mut()
+                .find(|advisory| advisory.id == patch.id)
+            {
+                advisory.packages = patch.packages;
+            }
+        }
+        res.out.push_str(&info);
+        return Ok(res);
+    }
+
+    fn update_advisories(
+        &self,
+        filter: &AdvisoryFilter,
+        _advisories: &[Advisory],
+        dry_run: bool,
+    ) -> Result<PackagesResponse, PackagesError> {
+        let output = match filter {
+            AdvisoryFilter::Security => {
+                run_command(transaction("patch", dry_run).args(["--category", "security"]))?
+            }
+            AdvisoryFilter::Id(id) => run_command(
+                transaction("install", dry_run)
+                    .args(["-t", "patch"])
+                    .arg(id),
+            )?,
+        };
+        return parse_output(output, parse_update);
+    }
+
+    fn autoremove(&self, dry_run: bool) -> Result<PackagesResponse, PackagesError> {
+        // zypper has no autoremove, but lists the unneeded packages nothing installed requires.
+        // Not the orphaned ones, which no repository provides and are often installed on purpose
+        let unneeded = run_command(native_command("zypper").args([
+            "--non-interactive",
+            "packages",
+            "--unneeded",
+        ]))?;
+        let names: Vec<String> = parse_package_table("autoremove", &unneeded)?
+            .packages
+            .into_iter()
+            .map(|package| package.name)
+            .collect();
+        if names.is_empty() {
+            let mut res = autoremoved(response("uninstall"));
+            res.out = unneeded;
+            return Ok(res);
+        }
+        let output = run_command(transaction("remove", dry_run).args(names))?;
+        return Ok(autoremoved(parse_output(output, parse_uninstall)?));
+    }
+
+    fn owner(&self, path: &str) -> Result<PackagesResponse, PackagesError> {
+        return rpm::owner(path);
+    }
+
+    fn files(&self, package: &str) -> Result<PackagesResponse, PackagesError> {
+        return rpm::files(package);
+    }
+
+    fn verify(&self, packages: &[String]) -> Result<PackagesResponse, PackagesError> {
+        return rpm::verify(packages);
+    }
+
+    fn clean(&self) -> Result<PackagesResponse, PackagesError> {
+        return clean_cache(
+            native_command("zypper").args(["--non-interactive", "clean", "--all"]),
+            &["zypp"],
+        );
+    }
+
+    fn holds(&self) -> Result<PackagesResponse, PackagesError> {
+        // "zypper locks" has no xml output, the locks file it reads is simpler
+        let output = read_file(&etc_path("zypp/locks"))?;
+        return parse_output(output, parse_locks);
+    }
+}
+
+/// Creates a noninteractive zypper command that changes packages.
+/// With "--dry-run", zypper prints the same install-summary without changing anything
+fn transaction(subcommand: &str, dry_run: bool) -> Command {
+    let mut command = native_command("zypper");
+    command.args(["--non-interactive", "--xmlout", subcommand]);
+    if dry_run {
+        command.arg("--dry-run");
+    }
+    return command;
+}
+
+/// Gets the parser for the output of a subcommand, used by the fixture tests
+#[cfg(test)]
+pub(super) fn parser(subcommand: &str) -> Option<Parser> {
+    return match subcommand {
+        "list" => Some(parse_list),
+        "info" => Some(parse_info),
+        "search" => Some(parse_search),
+        "refresh" => Some(parse_refresh),
+        "update" => Some(parse_update),
+        "install" => Some(parse_install),
+        "uninstall" => Some(parse_uninstall),
+        "repos" => Some(repos::parse_ini),
+        "history" => Some(parse_history),
+        "holds" => Some(parse_locks),
+        "advisories" => Some(parse_patches),
+        "patch_info" => Some(parse_patch_info),
+        "unneeded" => Some(|output| parse_package_table("autoremove", output)),
+        "orphaned" => Some(|output| parse_package_table("list", output)),
+        _ => None,
+    };
+}
+
+/// Parse list output
+fn parse_list(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("list");
+    let doc = parse_xml(output)?;
+    for node in doc.descendants().filter(|n| n.has_tag_name("solvable")) {
+        res.packages.push(solvable_to_package(node));
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+fn parse_xml(output: &str) -> Result<Document<'_>, PackagesError> {
+    return Document::parse(output).map_err(|error| {
+        let line = output
+            .lines()
+            .nth(error.pos().row as usize - 1)
+            .unwrap_or_default();
+        SysadminError::parse(line, &error.to_string())
+    });
+}
+
+/// Converts a "solvable" element from zypper's XML output into a Package, e.g.
+/// <solvable status="installed" name="curl" edition="8.6.0-1.1" arch="x86_64" repository="repo-oss"/>
+fn solvable_to_package(node: Node) -> Package {
+    let attribute = |name: &str| node.attribute(name).map(|value| value.to_string());
+    return Package {
+        name: attribute("name").unwrap_or_default(),
+        arch: attribute("arch").unwrap_or_default(),
+        version: attribute("edition"),
+        old_version: attribute("edition-old"),
+        repository: attribute("repository"),
+        description: attribute("summary"),
+        installed: node.attribute("status") == Some("installed"),
+        ..Default::default()
+    };
+}
+
+/// Parse info output.
+/// zypper doesn't structure "info" in its XML output, the details are
+/// printed as "Key : Value" lines inside of "message" elements
+fn parse_info(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("info");
+    let text = match Document::parse(output) {
+        Ok(doc) => doc
+            .descendants()
+            .filter(|n| n.has_tag_name("message"))
+            .filter_map(|n| n.text())
+            .collect::<Vec<&str>>()
+            .join("\n"),
+        Err(_) => output.to_string(),
+    };
+
+    for part in text.split("Information for package").skip(1) {
+        let mut package = Package::default();
+        let mut description: Vec<String> = vec![];
+        let mut in_description = false;
+
+        for line in part.lines() {
+            if in_description && line.starts_with(' ') {
+                description.push(line.trim().to_string());
+                continue;
+            }
+            let Some((field, value)) = line.split_once(" : ") else {
+                continue;
+            };
+            in_description = false;
+            let value = value.trim().to_string();
+            match field.trim() {
+                "Repository" => package.repository = Some(value),
+                "Name" => package.name = value,
+                "Version" => package.version = Some(value),
+                "Arch" => package.arch = value,
+                "Installed Size" => package.size_bytes = parse_byte_size(&value),
+                "Installed" => package.installed = value.starts_with("Yes"),
+                "Upstream URL" => package.url = Some(value),
+                "License" => package.license = Some(value),
+                "Description" => {
+                    in_description = true;
+                    description.push(value);
+                }
+                _ => {}
+            }
+        }
+
+        if package.name.is_empty() {
+            continue;
+        }
+        if !description.is_empty() {
+            package.description = Some(description.join("\n").trim().to_string());
+        }
+        res.packages.push(package);
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Parse search output
+fn parse_search(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_list(output)?;
+    res.subcommand = "search";
+    if res.packages.is_empty() {
+        res.messages.push("Nothing matched search".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parse refresh output
+fn parse_refresh(_output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("refresh");
+    res.messages.push("Updated repos".to_string());
+    return Ok(res);
+}
+
+/// Parse update output
+fn parse_update(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_transaction("update", output)?;
+    if res.packages.is_empty() && res.uninstalled_packages.is_empty() {
+        res.messages.push("No updates available".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parse install output
+fn parse_install(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_transaction("install", output)?;
+    if res.packages.is_empty() {
+        res.messages
+            .push("Package(s) already installed".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parse uninstall output
+fn parse_uninstall(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = parse_transaction("uninstall", output)?;
+    if res.uninstalled_packages.is_empty() {
+        res.messages.push("No packages to uninstall".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parses the "install-summary" element zypper prints for every transaction
+fn parse_transaction(
+    subcommand: &'static str,
+    output: &str,
+) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response(subcommand);
+    let doc = parse_xml(output)?;
+    let Some(summary) = doc
+        .descendants()
+        .find(|n| n.has_tag_name("install-summary"))
+    else {
+        return Ok(res);
+    };
+
+    for section in summary.children().filter(|n| n.is_element()) {
+        let installing = match section.tag_name().name() {
+            "to-install"
+            | "to-upgrade"
+            | "to-downgrade"
+            | "to-reinstall"
+            | "to-upgrade-change-arch" => true,
+            "to-remove" => false,
+            _ => continue,
+        };
+        // Patches are applied through the packages they update, only those are listed
+        let solvables = section
+            .children()
+            .filter(|n| n.has_tag_name("solvable") && n.attribute("kind") != Some("patch"));
+        for node in solvables {
+            let mut package = solvable_to_package(node);
+            package.installed = installing;
+            if installing {
+                res.packages.push(package);
+            } else {
+                res.uninstalled_packages.push(package);
+            }
+        }
+    }
+
+    // "space-usage-diff" is the change of disk usage in bytes, negative when space is freed
+    let space = summary
+        .attribute("space-usage-diff")
+        .and_then(|diff| diff.parse::<i64>().ok())
+        .unwrap_or_default();
+    if space < 0 {
+        res.uninstalled_packages_size_bytes = Some(space.unsigned_abs());
+    } else if space > 0 {
+        res.packages_size_bytes = Some(space as u64);
+    }
+
+    if !res.packages.is_empty() {
+        res.packages_length = Some(res.packages.len());
+    }
+    if !res.uninstalled_packages.is_empty() {
+        res.uninstalled_packages_length = Some(res.uninstalled_packages.len());
+    }
+    return Ok(res);
+}
+
+/// Parse "/var/log/zypp/history", one "|" separated line for every command and change, e.g.
+/// "2024-04-01 10:00:00|command|root@host|'zypper' 'in' 'curl'|" followed by
+/// "2024-04-01 10:00:02|install|curl|8.6.0-1.1|x86_64|root@host|repo-oss|<checksum>|"
+fn parse_history(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("history");
+    // Upgrades are logged as installs, so the last installed version of every package is the old one
+    let mut installed: HashMap<String, String> = HashMap::new();
+    let mut current: Option<Transaction> = None;
+    for line in output.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('|').map(|field| field.trim()).collect();
+        if fields.len() < 4 {
+            continue;
+        }
+        let installing = match fields[1] {
+            "command" => {
+                if let Some(transaction) = current.take() {
+                    res.transactions.push(transaction);
+                }
+                let mut transaction = Transaction::new(String::new());
+                transaction.timestamp = Some(fields[0].to_string());
+                transaction.user = history_user(fields[2]);
+                transaction.command = Some(fields[3].replace('\'', ""));
+                current = Some(transaction);
+                continue;
+            }
+            "install" => true,
+            "remove" => false,
+            // Repository changes like "radd" and "rremove"
+            _ => continue,
+        };
+        if fields.len() < 5 {
+            return Err(SysadminError::parse(line, "expected 5 fields"));
+        }
+        // Changes made without zypper, e.g. by YaST, aren't preceded by a command
+        let transaction = current.get_or_insert_with(|| {
+            let mut transaction = Transaction::new(String::new());
+            transaction.timestamp = Some(fields[0].to_string());
+            transaction.user = fields.get(5).and_then(|user| history_user(user));
+            return transaction;
+        });
+        let name = fields[2].to_string();
+        let version = fields[3].to_string();
+        let old_version = match installing {
+            true => installed.insert(name.clone(), version.clone()),
+            false => {
+                installed.remove(&name);
+                None
+            }
+        };
+        transaction.add_action(match (installing, &old_version) {
+            (true, Some(_)) => "upgrade",
+            (true, None) => "install",
+            (false, _) => "remove",
+        });
+        transaction.packages.push(Package {
+            name,
+            arch: fields[4].to_string(),
+            version: Some(version),
+            old_version,
+            repository: fields
+                .get(6)
+                .filter(|repository| installing && !repository.is_empty())
+                .map(|repository| repository.to_string()),
+            installed: installing,
+            ..Default::default()
+        });
+    }
+    if let Some(transaction) = current {
+        res.transactions.push(transaction);
+    }
+    // Commands that didn't change anything, like "zypper refresh", aren't transactions
+    res.transactions
+        .retain(|transaction| !transaction.packages.is_empty());
+    for (number, transaction) in res.transactions.iter_mut().enumerate() {
+        transaction.id = (number + 1).to_string();
+    }
+    res.transactions.reverse();
+    if res.transactions.is_empty() {
+        res.messages
+            .push("No transactions in the history".to_string());
+    }
+    return Ok(res);
+}
+
+/// Parse list-patches output, e.g.
+/// "<update kind="patch" name="openSUSE-SLE-15.5-2024-1234" status="needed" category="security" severity="important">"
+/// with a "summary", an "issue-date" and an "issue-list" of CVEs and bug reports
+fn parse_patches(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("advisories");
+    let doc = parse_xml(output)?;
+    let patches = doc.descendants().filter(|n| {
+        n.has_tag_name("update")
+            && n.attribute("kind") == Some("patch")
+            && n.parent()
+                .is_some_and(|parent| parent.has_tag_name("update-list"))
+    });
+    for patch in patches {
+        let child = |name: &str| patch.children().find(|n| n.has_tag_name(name));
+        res.advisories.push(Advisory {
+            id: patch.attribute("name").unwrap_or_default().to_string(),
+            kind: patch.attribute("category").map(|kind| kind.to_string()),
+            severity: patch
+                .attribute("severity")
+                .filter(|severity| *severity != "unspecified")
+                .map(|severity| severity.to_string()),
+            title: child("summary")
+                .and_then(|n| n.text())
+                .map(|title| title.to_string()),
+            issued: child("issue-date")
+                .and_then(|n| n.attribute("time"))
+                .and_then(|time| time.parse().ok())
+                .map(format_unix_time),
+            cves: child("issue-list")
+                .map(|issues| {
+                    issues
+                        .children()
+                        .filter(|n| n.attribute("type") == Some("cve"))
+                        .filter_map(|n| n.attribute("id"))
+                        .map(|id| id.to_string())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            packages: vec![],
+        });
+    }
+    return Ok(res);
+}
+
+/// Parse the "info -t patch" output of patches, the packages a patch updates are its
+/// conflicts with older versions, e.g. "curl.x86_64 < 8.6.0-1.1" below "Conflicts : [4]"
+fn parse_patch_info(output: &str) -> Result<PackagesResponse, PackagesError> {
+    const ARCHES: [&str; 9] = [
+        "x86_64", "i586", "i686", "aarch64", "armv7hl", "ppc64le", "s390x", "riscv64", "noarch",
+    ];
+    let mut res = response("advisories");
+    let mut conflicts = false;
+    for line in output.lines() {
+        if let Some(name) = line
+            .strip_prefix("Information for patch ")
+            .and_then(|rest| rest.strip_suffix(':'))
+        {
+            res.advisories.push(Advisory {
+                id: name.to_string(),
+                kind: None,
+                severity: None,
+                title: None,
+                issued: None,
+                cves: vec![],
+                packages: vec![],
+            });
+            conflicts = false;
+            continue;
+        }
+        if !line.starts_with(char::is_whitespace) {
+            conflicts = line.starts_with("Conflicts");
+            continue;
+        }
+        let Some(advisory) = res.advisories.last_mut().filter(|_| conflicts) else {
+            continue;
+        };
+        let Some((package, version)) = line.trim().split_once(" < ") else {
+            continue;
+        };
+        let (name, arch) = match package.rsplit_once('.') {
+            Some((name, arch)) if ARCHES.contains(&arch) => (name, arch),
+            // Source packages aren't installed
+            Some((_, "src")) => continue,
+            _ => (package, ""),
+        };
+        advisory.packages.push(Package {
+            name: name.to_string(),
+            arch: arch.to_string(),
+            version: Some(version.trim().to_string()),
+            ..Default::default()
+        });
+    }
+    return Ok(res);
+}
+
+/// Parse the table of "zypper packages --unneeded" or "--orphaned", which has no xml output, e.g.
+///
+/// S  | Repository | Name    | Version  | Arch
+/// ---+------------+---------+----------+-------
+/// i  | repo-oss   | libfoo1 | 1.2-3.1  | x86_64
+/// i  | @System    | foo     | 1.0-1    | x86_64
+///
+/// "@System" is the repository of packages no repository provides
+fn parse_package_table(
+    subcommand: &'static str,
+    output: &str,
+) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response(subcommand);
+    for line in output.lines() {
+        let columns: Vec<&str> = line.split('|').map(|column| column.trim()).collect();
+        let [status, repository, name, version, arch] = columns[..] else {
+            continue;
+        };
+        if status == "S" {
+            continue;
+        }
+        res.packages.push(Package {
+            name: name.to_string(),
+            arch: arch.to_string(),
+            version: Some(version.to_string()),
+            repository: Some(repository.to_string()).filter(|repository| repository != "@System"),
+            installed: status.starts_with('i'),
+            ..Default::default()
+        });
+    }
+    return Ok(res);
+}
+
+/// Parse "/etc/zypp/locks", a paragraph for every lock, e.g.
+/// "type: package", "match_type: glob" and "solvable_name: curl".
+/// Locks of a version have a condition like "version: = 8.6.0-1.1"
+fn parse_locks(output: &str) -> Result<PackagesResponse, PackagesError> {
+    let mut res = response("holds");
+    for paragraph in output.split("\n\n") {
+        let mut name = None;
+        let mut version = None;
+        let mut kind = "package";
+        for line in paragraph.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "solvable_name" => name = Some(value),
+                "version" => version = Some(value.trim_start_matches(['=', ' ']).to_string()),
+                "type" => kind = value,
+                _ => {}
+            }
+        }
+        // Locks of patterns and patches aren't package holds
+        let Some(name) = name.filter(|_| kind == "package") else {
+            continue;
+        };
+        res.packages.push(Package {
+            name: name.to_string(),
+            version,
+            // Locks don't tell whether the package is installed
+            installed: false,
+            ..Default::default()
+        });
+    }
+    if res.packages.is_empty() {
+        res.messages.push("No packages are held".to_string());
+    }
+    res.packages_length = Some(res.packages.len());
+    return Ok(res);
+}
+
+/// Gets the user of a history line like "root@host"
+fn history_user(user: &str) -> Option<String> {
+    let user = user.split('@').next().unwrap_or_default();
+    if user.is_empty() {
+        return None;
+    }
+    return Some(user.to_string());
+}
+
+/// Parses a line zypper prints with "--xmlout" while it runs, e.g.
+/// "<progress id="install-resolvable" name="(1/3) Installing: ripgrep-14.1.0-1.2.x86_64" value="50"/>"
+/// or "<message type="info">Retrieving: ripgrep-14.1.0-1.2.x86_64 (repo-oss) (1/3), 1.5 MiB</message>"
+pub(super) fn parse_progress(line: &str) -> Option<ProgressEvent> {
+    let doc = Document::parse(line.trim()).ok()?;
+    let node = doc.root_element();
+    let text = match node.tag_name().name() {
+        "progress" => node.attribute("name")?,
+        "message" => node.text()?,
+        _ => return None,
+    };
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let (action, package) = match words.as_slice() {
+        [counter, action, package, ..] if counter.starts_with('(') => (*action, package),
+        [action, package, ..] => (*action, package),
+        _ => return None,
+    };
+    let phase = match action {
+        "Retrieving:" => Phase::Downloading,
+        "Installing:" => Phase::Installing,
+        "Removing" | "Removing:" => Phase::Removing,
+        "Retrieving" | "Building" | "Refreshing" => Phase::Refreshing,
+        _ => return None,
+    };
+    let mut event = ProgressEvent::new(phase, line);
+    if phase != Phase::Refreshing {
+        event.package = Some(package.to_string());
+    }
+    // Downloads have their counter after the repository, e.g. "(repo-oss) (1/3), 1.5 MiB"
+    event.percent = words
+        .iter()
+        .find_map(|word| counter_percent(word.trim_end_matches(',')))
+        .or(node.attribute("value").and_then(|value| value.parse().ok()));
+    if let Some((_, size)) = text.rsplit_once(", ") {
+        event.download_bytes = parse_byte_size(size);
+    }
+    return Some(event);
 }
@@ -0,0 +1,159 @@
+use super::*;
+use crate::os::OsRelease;
+use crate::packages::simplify_byte_size;
+use std::fs;
+
+/// Facts about the host, read from "/etc/os-release" and "/proc".
+/// Anything that can't be read is left out
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OsInfo {
+    /// e.g. "fedora"
+    pub os_id: Option<String>,
+    /// e.g. "Fedora Linux 40 (Workstation Edition)"
+    pub os_name: Option<String>,
+    /// e.g. "40"
+    pub os_version: Option<String>,
+    /// e.g. "6.8.4-300.fc40.x86_64"
+    pub kernel: Option<String>,
+    pub hostname: Option<String>,
+    pub uptime_seconds: Option<u64>,
+    /// Unix timestamp of the boot
+    pub boot_time: Option<u64>,
+    pub cpu_model: Option<String>,
+    pub cpu_count: Option<usize>,
+    pub memory_total_bytes: Option<u64>,
+    pub memory_available_bytes: Option<u64>,
+    pub swap_total_bytes: Option<u64>,
+    pub swap_free_bytes: Option<u64>,
+}
+
+pub fn match_system_command(systemcli: &SystemArgs, output_type: OutputType) -> Exit {
+    match &systemcli.command {
+        SystemCommands::Info => {
+            let info = os_info();
+            return Exit {
+                exit_code: 0,
+                real_command: "none",
+                out: format_os_info(&info),
+                packages_response: None,
+                error_message: None,
+                error_kind: None,
+                os_info: match output_type {
+                    OutputType::Stdout => None,
+                    _ => Some(info),
+                },
+            };
+        }
+    }
+}
+
+/// Collects the OsInfo of this host
+pub fn os_info() -> OsInfo {
+    let os = OsRelease::read().ok();
+    let meminfo = read_proc("meminfo").unwrap_or_default();
+    let cpuinfo = read_proc("cpuinfo").unwrap_or_default();
+    let processors = cpuinfo
+        .lines()
+        .filter(|line| line.starts_with("processor"))
+        .count();
+
+    return OsInfo {
+        os_id: os.as_ref().map(|os| os.id.clone()),
+        os_name: os
+            .as_ref()
+            .and_then(|os| os.pretty_name.clone().or(os.name.clone())),
+        os_version: os.as_ref().and_then(|os| os.version_id.clone()),
+        kernel: read_proc("sys/kernel/osrelease").map(|kernel| kernel.trim().to_string()),
+        hostname: read_proc("sys/kernel/hostname").map(|hostname| hostname.trim().to_string()),
+        // e.g. "1658.34 1427.99", the uptime and the idle time in seconds
+        uptime_seconds: read_proc("uptime")
+            .and_then(|uptime| uptime.split_whitespace().next()?.parse::<f64>().ok())
+            .map(|uptime| uptime as u64),
+        // e.g. "btime 1792320986"
+        boot_time: read_proc("stat").and_then(|stat| {
+            stat.lines()
+                .find_map(|line| line.strip_prefix("btime "))
+                .and_then(|btime| btime.trim().parse().ok())
+        }),
+        // e.g. "model name	: Intel(R) Xeon(R) Processor", ARM only has "Hardware" or "Model"
+        cpu_model: ["model name", "Hardware", "Model", "cpu model"]
+            .iter()
+            .find_map(|field| cpuinfo_value(&cpuinfo, field)),
+        cpu_count: match processors {
+            0 => std::thread::available_parallelism()
+                .ok()
+                .map(|count| count.get()),
+            _ => Some(processors),
+        },
+        memory_total_bytes: meminfo_bytes(&meminfo, "MemTotal"),
+        memory_available_bytes: meminfo_bytes(&meminfo, "MemAvailable"),
+        swap_total_bytes: meminfo_bytes(&meminfo, "SwapTotal"),
+        swap_free_bytes: meminfo_bytes(&meminfo, "SwapFree"),
+    };
+}
+
+fn read_proc(path: &str) -> Option<String> {
+    return fs::read_to_string(format!("/proc/{}", path)).ok();
+}
+
+fn cpuinfo_value(cpuinfo: &str, field: &str) -> Option<String> {
+    return cpuinfo.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        match key.trim() == field {
+            true => Some(value.trim().to_string()),
+            false => None,
+        }
+    });
+}
+
+/// Gets a value from "/proc/meminfo" in bytes, e.g. "MemTotal:        6147400 kB"
+fn meminfo_bytes(meminfo: &str, field: &str) -> Option<u64> {
+    let line = meminfo
+        .lines()
+        .find(|line| line.split_once(':').is_some_and(|(key, _)| key == field))?;
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    return Some(kilobytes * 1024);
+}
+
+/// Formats the OsInfo for the "stdout" output type
+fn format_os_info(info: &OsInfo) -> String {
+    let mut lines: Vec<String> = vec![];
+    if let Some(os_name) = &info.os_name {
+        lines.push(format!("OS: {}", os_name));
+    }
+    if let Some(kernel) = &info.kernel {
+        lines.push(format!("Kernel: {}", kernel));
+    }
+    if let Some(hostname) = &info.hostname {
+        lines.push(format!("Hostname: {}", hostname));
+    }
+    if let Some(uptime) = info.uptime_seconds {
+        lines.push(format!(
+            "Uptime: {}d {}h {}m",
+            uptime / 86400,
+            uptime % 86400 / 3600,
+            uptime % 3600 / 60
+        ));
+    }
+    match (&info.cpu_model, info.cpu_count) {
+        (Some(model), Some(count)) => lines.push(format!("CPU: {} ({} CPUs)", model, count)),
+        (Some(model), None) => lines.push(format!("CPU: {}", model)),
+        (None, Some(count)) => lines.push(format!("CPU: {} CPUs", count)),
+        (None, None) => {}
+    }
+    if let (Some(available), Some(total)) = (info.memory_available_bytes, info.memory_total_bytes) {
+        lines.push(format!(
+            "Memory: {} available of {}",
+            simplify_byte_size(available),
+            simplify_byte_size(total)
+        ));
+    }
+    if let (Some(free), Some(total)) = (info.swap_free_bytes, info.swap_total_bytes) {
+        lines.push(format!(
+            "Swap: {} free of {}",
+            simplify_byte_size(free),
+            simplify_byte_size(total)
+        ));
+    }
+    return lines.join("\n");
+}
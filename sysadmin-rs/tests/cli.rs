@@ -0,0 +1,1290 @@
+//! End-to-end tests of the sysadmin binary.
+//!
+//! The native programs are replaced by "tests/mock/native" through SYSADMIN_NATIVE_PATH,
+//! which replays the responses queued with Mock::respond, and the OS is picked
+//! with an os-release file in SYSADMIN_OS_RELEASE. PATH only contains the
+//! package managers added with Mock::install, for probing the backend.
+
+use serde_json::{json, Value};
+use std::cell::Cell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const NATIVE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/mock/native");
+
+struct Mock {
+    dir: PathBuf,
+    native: PathBuf,
+    responses: Cell<usize>,
+}
+
+impl Mock {
+    /// Creates an empty mock directory for a test, with an os-release file of the given contents
+    fn new(test: &str, os_release: &str) -> Mock {
+        let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(test);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("bin")).unwrap();
+        fs::write(dir.join("os-release"), os_release).unwrap();
+        return Mock {
+            dir,
+            native: PathBuf::from(NATIVE),
+            responses: Cell::new(0),
+        };
+    }
+
+    /// Creates a mock for an OS id
+    fn os(test: &str, os_id: &str) -> Mock {
+        return Mock::new(test, &format!("NAME=\"Test\"\nID={}\n", os_id));
+    }
+
+    /// Queues the response for the next call of a native program
+    fn respond(&self, stdout: &str, stderr: &str, exit_code: i32) -> &Mock {
+        let n = self.responses.get() + 1;
+        self.responses.set(n);
+        fs::write(self.dir.join(format!("{}.stdout", n)), stdout).unwrap();
+        fs::write(self.dir.join(format!("{}.stderr", n)), stderr).unwrap();
+        fs::write(self.dir.join(format!("{}.exit", n)), exit_code.to_string()).unwrap();
+        return self;
+    }
+
+    /// Runs a shell script when the last queued response is replayed, e.g. to remove cached files
+    fn effect(&self, script: &str) -> &Mock {
+        let n = self.responses.get();
+        fs::write(self.dir.join(format!("{}.sh", n)), script).unwrap();
+        return self;
+    }
+
+    /// Adds a package manager to PATH, it can only be found and never runs
+    fn install(&self, program: &str) -> &Mock {
+        fs::write(self.dir.join("bin").join(program), "").unwrap();
+        return self;
+    }
+
+    /// Writes a package manager log, e.g. "apt/history.log"
+    fn log(&self, path: &str, contents: &str) -> &Mock {
+        let path = self.dir.join("log").join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+        return self;
+    }
+
+    /// Writes a configuration file, e.g. "apk/repositories"
+    fn etc(&self, path: &str, contents: &str) -> &Mock {
+        let path = self.dir.join("etc").join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+        return self;
+    }
+
+    /// Writes a file in the cache of a package manager, e.g. "apt/archives/curl.deb"
+    fn cache(&self, path: &str, contents: &str) -> &Mock {
+        let path = self.dir.join("cache").join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+        return self;
+    }
+
+    /// Reads a configuration file, None if it doesn't exist
+    fn read_etc(&self, path: &str) -> Option<String> {
+        return fs::read_to_string(self.dir.join("etc").join(path)).ok();
+    }
+
+    fn run(&self, args: &[&str]) -> Output {
+        return Command::new(env!("CARGO_BIN_EXE_sysadmin"))
+            .args(args)
+            .env("SYSADMIN_NATIVE_PATH", &self.native)
+            .env("SYSADMIN_OS_RELEASE", self.dir.join("os-release"))
+            .env("SYSADMIN_LOG_DIR", self.dir.join("log"))
+            .env("SYSADMIN_ETC_DIR", self.dir.join("etc"))
+            .env("SYSADMIN_CACHE_DIR", self.dir.join("cache"))
+            .env("MOCK_DIR", &self.dir)
+            .env("PATH", self.dir.join("bin"))
+            .output()
+            .unwrap();
+    }
+
+    /// Runs the binary with json output and returns the Exit
+    fn run_json(&self, args: &[&str]) -> Value {
+        let output = self.run(&[&["-o", "json"], args].concat());
+        return serde_json::from_slice(&output.stdout).unwrap();
+    }
+
+    /// Gets the native calls made so far as "<program> <args>"
+    fn calls(&self) -> Vec<String> {
+        return fs::read_to_string(self.dir.join("calls"))
+            .unwrap_or_default()
+            .lines()
+            .map(|line| line.to_string())
+            .collect();
+    }
+}
+
+fn fixture(path: &str) -> String {
+    return fs::read_to_string(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(path),
+    )
+    .unwrap();
+}
+
+fn snapshot(path: &str) -> Value {
+    return serde_json::from_str(&fixture(path)).unwrap();
+}
+
+#[test]
+fn refresh_for_every_os_id() {
+    let cases = [
+        ("fedora", "dnf", "dnf makecache --refresh"),
+        ("ultramarine", "dnf", "dnf makecache --refresh"),
+        ("alpine", "apk", "apk update"),
+        ("wolfi", "apk", "apk update"),
+        ("debian", "apt", "apt update"),
+        ("ubuntu", "apt", "apt update"),
+        ("pop", "apt", "apt update"),
+        ("opensuse-tumbleweed", "zypper", "zypper --xmlout refresh"),
+        ("opensuse-leap", "zypper", "zypper --xmlout refresh"),
+        ("arch", "pacman", "pacman -Sy"),
+        ("endeavouros", "pacman", "pacman -Sy"),
+        ("manjaro", "pacman", "pacman -Sy"),
+    ];
+    for (os_id, manager, call) in cases {
+        let mock = Mock::os(&format!("refresh_{}", os_id), os_id);
+        let exit = mock.run_json(&["packages", "refresh"]);
+        assert_eq!(exit["exit_code"], 0, "{}", os_id);
+        assert_eq!(exit["real_command"], manager, "{}", os_id);
+        assert_eq!(exit["packages_response"]["messages"][0], "Updated repos");
+        assert_eq!(mock.calls(), vec![call], "{}", os_id);
+    }
+}
+
+#[test]
+fn falls_back_to_id_like() {
+    let mock = Mock::new(
+        "falls_back_to_id_like",
+        "ID=linuxmint\nID_LIKE=\"ubuntu debian\"\n",
+    );
+    let exit = mock.run_json(&["packages", "refresh"]);
+    assert_eq!(exit["real_command"], "apt");
+    assert_eq!(mock.calls(), vec!["apt update"]);
+}
+
+#[test]
+fn list_json_exit() {
+    let mock = Mock::os("list_json_exit", "debian");
+    let output = fixture("apt/list/installed.txt");
+    mock.respond(&output, "", 0);
+    let exit = mock.run_json(&["packages", "list", "--installed"]);
+    assert_eq!(
+        exit,
+        json!({
+            "exit_code": 0,
+            "real_command": "apt",
+            "out": output,
+            "packages_response": snapshot("apt/list/installed.json"),
+            "error_message": null,
+            "error_kind": null,
+            "os_info": null,
+        })
+    );
+    assert_eq!(mock.calls(), vec!["apt list --installed"]);
+}
+
+#[test]
+fn dnf_list_combines_repoqueries() {
+    let mock = Mock::os("dnf_list_combines_repoqueries", "fedora");
+    mock.respond("dnf5 version 5.1.15\n", "", 0)
+        .respond(&fixture("dnf/list/installed.txt"), "", 0)
+        .respond(&fixture("dnf/list/dnf4_available.txt"), "", 0);
+    let exit = mock.run_json(&["packages", "list"]);
+    assert_eq!(exit["exit_code"], 0);
+    let names: Vec<&str> = exit["packages_response"]["packages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|package| package["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["bash", "python3.12", "shadow-utils", "ripgrep"]);
+
+    let calls = mock.calls();
+    assert_eq!(calls.len(), 3);
+    assert_eq!(calls[0], "dnf --version");
+    assert!(calls[1].starts_with("dnf repoquery") && calls[1].ends_with("--installed"));
+    assert!(calls[2].starts_with("dnf repoquery") && calls[2].ends_with("--available"));
+}
+
+#[test]
+fn dnf5_list_uses_json_repoquery() {
+    let mock = Mock::os("dnf5_list_uses_json_repoquery", "fedora");
+    mock.respond("dnf5 version 5.2.12.0\n", "", 0).respond(
+        &fixture("dnf/json_list/installed.txt"),
+        "",
+        0,
+    );
+    let exit = mock.run_json(&["packages", "list", "--installed"]);
+    assert_eq!(exit["exit_code"], 0);
+    assert_eq!(
+        exit["packages_response"]["packages"][1]["version"],
+        "2:4.15.1-2.fc40"
+    );
+    assert_eq!(
+        mock.calls(),
+        vec!["dnf --version", "dnf repoquery --quiet --json --installed"]
+    );
+}
+
+#[test]
+fn apt_list_extras_from_local_flags() {
+    let mock = Mock::os("apt_list_extras_from_local_flags", "debian");
+    mock.respond(
+        "Listing...\ncurl/stable,now 7.88.1-10+deb12u8 amd64 [installed]\nfoo/now 1.0 amd64 [installed,local]\nripgrep/stable 13.0.0-4+b2 amd64\n",
+        "",
+        0,
+    );
+    let exit = mock.run_json(&["packages", "list", "--extras", "--available"]);
+    assert_eq!(exit["exit_code"], 0);
+    let names: Vec<&str> = exit["packages_response"]["packages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|package| package["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["foo", "ripgrep"]);
+    assert_eq!(mock.calls(), vec!["apt list"]);
+}
+
+#[test]
+fn pacman_list_available_from_sync_databases() {
+    let mock = Mock::os("pacman_list_available_from_sync_databases", "arch");
+    mock.respond(&fixture("pacman/sync_list/repos.txt"), "", 0);
+    let exit = mock.run_json(&["packages", "list", "--available", "ripgrep", "vim"]);
+    assert_eq!(exit["exit_code"], 0);
+    let packages = &exit["packages_response"]["packages"];
+    assert_eq!(packages.as_array().unwrap().len(), 1);
+    assert_eq!(packages[0]["name"], "ripgrep");
+    assert_eq!(packages[0]["repository"], "extra");
+    assert_eq!(mock.calls(), vec!["pacman -Sl"]);
+}
+
+#[test]
+fn zypper_list_installed_and_available_is_a_union() {
+    let mock = Mock::os(
+        "zypper_list_installed_and_available_is_a_union",
+        "opensuse-tumbleweed",
+    );
+    mock.respond(&fixture("zypper/list/installed.txt"), "", 0);
+    let exit = mock.run_json(&["packages", "list", "--installed", "--available"]);
+    assert_eq!(exit["exit_code"], 0);
+    assert_eq!(
+        mock.calls(),
+        vec!["zypper --xmlout search --details --type package"]
+    );
+}
+
+#[test]
+fn zypper_list_extras_from_orphaned_packages() {
+    let mock = Mock::os(
+        "zypper_list_extras_from_orphaned_packages",
+        "opensuse-tumbleweed",
+    );
+    mock.respond(&fixture("zypper/orphaned/orphaned.txt"), "", 0);
+    let exit = mock.run_json(&["packages", "list", "--extras", "libfoo1"]);
+    assert_eq!(exit["exit_code"], 0);
+    let packages = &exit["packages_response"]["packages"];
+    assert_eq!(packages.as_array().unwrap().len(), 1);
+    assert_eq!(packages[0]["name"], "libfoo1");
+    assert_eq!(packages[0]["repository"], Value::Null);
+    assert_eq!(
+        mock.calls(),
+        vec!["zypper --non-interactive packages --orphaned"]
+    );
+}
+
+#[test]
+fn flatpak_list_extras_unsupported() {
+    let mock = Mock::os("flatpak_list_extras_unsupported", "fedora");
+    mock.install("flatpak");
+    let exit = mock.run_json(&["packages", "--source", "flatpak", "list", "--extras"]);
+    assert_eq!(exit["exit_code"], 2);
+    assert_eq!(exit["error_kind"], "unsupported_operation");
+    assert_eq!(mock.calls(), Vec::<String>::new());
+}
+
+#[test]
+fn snap_list_available_unsupported() {
+    let mock = Mock::os("snap_list_available_unsupported", "ubuntu");
+    mock.install("snap");
+    let exit = mock.run_json(&["packages", "--source", "snap", "list", "--available"]);
+    assert_eq!(exit["exit_code"], 2);
+    assert_eq!(exit["error_kind"], "unsupported_operation");
+    assert_eq!(mock.calls(), Vec::<String>::new());
+}
+
+#[test]
+fn apk_info_installed_from_list() {
+    let mock = Mock::os("apk_info_installed_from_list", "alpine");
+    let info = fixture("apk/info/curl.txt") + "\n" + &fixture("apk/info/not_installed.txt");
+    mock.respond(&info, "", 0)
+        .respond("curl-8.5.0-r0 x86_64 {curl} (curl) [installed]\n", "", 0);
+    let exit = mock.run_json(&["packages", "info", "curl", "ripgrep"]);
+    assert_eq!(exit["exit_code"], 0);
+    let packages = &exit["packages_response"]["packages"];
+    assert_eq!(packages[0]["name"], "curl");
+    assert_eq!(packages[0]["installed"], true);
+    assert_eq!(packages[1]["name"], "ripgrep");
+    assert_eq!(packages[1]["installed"], false);
+    assert_eq!(
+        mock.calls(),
+        vec![
+            "apk info -d -w -s --license curl ripgrep",
+            "apk list -I curl ripgrep"
+        ]
+    );
+}
+
+#[test]
+fn stdout_prints_native_output() {
+    let mock = Mock::os("stdout_prints_native_output", "arch");
+    let output = fixture("pacman/list/installed.txt");
+    mock.respond(&output, "", 0);
+    let result = mock.run(&["packages", "list"]);
+    assert!(result.status.success());
+    assert_eq!(String::from_utf8(result.stdout).unwrap(), output + "\n");
+}
+
+#[test]
+fn msgpack_exit_is_positional() {
+    let mock = Mock::os("msgpack_exit_is_positional", "alpine");
+    mock.respond(&fixture("apk/search/ripgrep.txt"), "", 0);
+    let result = mock.run(&["-o", "msgpack", "packages", "search", "ripgrep"]);
+    let exit: Value = rmp_serde::from_slice(&result.stdout).unwrap();
+    assert_eq!(exit[0], 0);
+    assert_eq!(exit[1], "apk");
+    let packages_response = &exit[3];
+    assert_eq!(packages_response[0], "search");
+    assert_eq!(packages_response[2][0][0], "ripgrep");
+    assert_eq!(packages_response[2].as_array().unwrap().len(), 2);
+    assert_eq!(exit[4], Value::Null);
+    assert_eq!(exit[5], Value::Null);
+}
+
+#[test]
+fn permission_denied() {
+    let mock = Mock::os("permission_denied", "ubuntu");
+    mock.respond(
+        "",
+        "E: Could not open lock file /var/lib/dpkg/lock-frontend - open (13: Permission denied)\n\
+         E: Unable to acquire the dpkg frontend lock (/var/lib/dpkg/lock-frontend), are you root?\n",
+        100,
+    );
+    let result = mock.run(&["-o", "json", "packages", "install", "ripgrep"]);
+    assert!(!result.status.success());
+    let exit: Value = serde_json::from_slice(&result.stdout).unwrap();
+    assert_eq!(exit["exit_code"], 126);
+    assert_eq!(exit["real_command"], "apt");
+    assert_eq!(exit["error_kind"], "permission_denied");
+    assert_eq!(exit["packages_response"], Value::Null);
+}
+
+#[test]
+fn command_failed() {
+    let mock = Mock::os("command_failed", "fedora");
+    mock.respond(
+        "",
+        "No match for argument: nope\nError: Unable to find a match: nope\n",
+        1,
+    );
+    let exit = mock.run_json(&["packages", "install", "nope"]);
+    assert_eq!(exit["exit_code"], 1);
+    assert_eq!(exit["error_kind"], "command_failed");
+    assert_eq!(
+        exit["error_message"],
+        "No match for argument: nope\nError: Unable to find a match: nope\n"
+    );
+}
+
+#[test]
+fn unparsable_output() {
+    let mock = Mock::os("unparsable_output", "opensuse-tumbleweed");
+    mock.respond(&fixture("zypper/list/malformed.txt"), "", 0);
+    let exit = mock.run_json(&["packages", "list"]);
+    assert_eq!(exit["exit_code"], 1);
+    assert_eq!(exit["real_command"], "zypper");
+    assert_eq!(exit["error_kind"], "parse_failure");
+}
+
+#[test]
+fn backend_missing() {
+    let mut mock = Mock::os("backend_missing", "debian");
+    mock.native = mock.dir.join("missing");
+    let exit = mock.run_json(&["packages", "list"]);
+    assert_eq!(exit["exit_code"], 127);
+    assert_eq!(exit["error_kind"], "backend_missing");
+}
+
+#[test]
+fn unsupported_os() {
+    let mock = Mock::os("unsupported_os", "haiku");
+    let exit = mock.run_json(&["packages", "list"]);
+    assert_eq!(exit["exit_code"], 1);
+    assert_eq!(exit["real_command"], "none");
+    assert_eq!(exit["error_kind"], "unsupported_os");
+    assert!(mock.calls().is_empty());
+}
+
+#[test]
+fn quoted_os_release_values() {
+    let mock = Mock::new(
+        "quoted_os_release_values",
+        "NAME=\"openSUSE Tumbleweed\"\nID=\"opensuse-tumbleweed\"\nID_LIKE=\"opensuse suse\"\n",
+    );
+    let exit = mock.run_json(&["packages", "refresh"]);
+    assert_eq!(exit["real_command"], "zypper");
+}
+
+#[test]
+fn derived_distributions_use_id_like() {
+    let cases = [
+        ("rocky", "rhel centos fedora", "dnf"),
+        ("almalinux", "rhel centos fedora", "dnf"),
+        ("centos", "rhel fedora", "dnf"),
+        ("ol", "fedora", "dnf"),
+        ("linuxmint", "ubuntu debian", "apt"),
+        ("zorin", "ubuntu", "apt"),
+        ("garuda", "arch", "pacman"),
+        ("postmarketos", "alpine", "apk"),
+    ];
+    for (os_id, id_like, manager) in cases {
+        let mock = Mock::new(
+            &format!("id_like_{}", os_id),
+            &format!("ID={}\nID_LIKE=\"{}\"\n", os_id, id_like),
+        );
+        let exit = mock.run_json(&["packages", "refresh"]);
+        assert_eq!(exit["real_command"], manager, "{}", os_id);
+    }
+}
+
+#[test]
+fn probes_installed_package_manager() {
+    let mock = Mock::new("probes_installed_package_manager", "ID=mystery\n");
+    mock.install("pacman");
+    let exit = mock.run_json(&["packages", "refresh"]);
+    assert_eq!(exit["real_command"], "pacman");
+    assert_eq!(mock.calls(), vec!["pacman -Sy"]);
+}
+
+#[test]
+fn probes_without_os_release() {
+    let mock = Mock::os("probes_without_os_release", "debian");
+    fs::remove_file(mock.dir.join("os-release")).unwrap();
+    mock.install("apk");
+    let exit = mock.run_json(&["packages", "refresh"]);
+    assert_eq!(exit["real_command"], "apk");
+}
+
+#[test]
+fn unknown_os_without_os_release() {
+    let mock = Mock::os("unknown_os_without_os_release", "debian");
+    fs::remove_file(mock.dir.join("os-release")).unwrap();
+    let exit = mock.run_json(&["packages", "refresh"]);
+    assert_eq!(exit["error_kind"], "unknown_os");
+}
+
+#[test]
+fn backend_override() {
+    let mock = Mock::os("backend_override", "fedora");
+    mock.install("dnf");
+    let exit = mock.run_json(&["packages", "--backend", "zypper", "refresh"]);
+    assert_eq!(exit["real_command"], "zypper");
+    let exit = mock.run_json(&["packages", "refresh", "-b", "apk"]);
+    assert_eq!(exit["real_command"], "apk");
+}
+
+#[test]
+fn system_info() {
+    let mock = Mock::new(
+        "system_info",
+        "NAME=\"Fedora Linux\"\nVERSION_ID=40\nID=fedora\nPRETTY_NAME=\"Fedora Linux 40 (Workstation Edition)\"\n",
+    );
+    let exit = mock.run_json(&["system", "info"]);
+    assert_eq!(exit["exit_code"], 0);
+    assert_eq!(exit["packages_response"], Value::Null);
+    let info = &exit["os_info"];
+    assert_eq!(info["os_id"], "fedora");
+    assert_eq!(info["os_name"], "Fedora Linux 40 (Workstation Edition)");
+    assert_eq!(info["os_version"], "40");
+    assert!(info["kernel"].is_string());
+    assert!(info["cpu_count"].as_u64().unwrap() > 0);
+    assert!(info["memory_total_bytes"].as_u64().unwrap() > 0);
+    assert!(mock.calls().is_empty());
+
+    let result = mock.run(&["system", "info"]);
+    let out = String::from_utf8(result.stdout).unwrap();
+    assert!(out.starts_with("OS: Fedora Linux 40 (Workstation Edition)\nKernel: "));
+}
+
+#[test]
+fn flatpak_source() {
+    let mock = Mock::os("flatpak_source", "fedora");
+    mock.respond(&fixture("flatpak/list/installed.txt"), "", 0);
+    let exit = mock.run_json(&["packages", "--source", "flatpak", "list"]);
+    assert_eq!(exit["real_command"], "flatpak");
+    assert_eq!(
+        exit["packages_response"],
+        snapshot("flatpak/list/installed.json")
+    );
+    let calls = mock.calls();
+    assert_eq!(calls.len(), 1);
+    assert!(calls[0].starts_with("flatpak list --columns="));
+}
+
+#[test]
+fn flatpak_user_installation() {
+    let mock = Mock::os("flatpak_user_installation", "ubuntu");
+    mock.respond(&fixture("flatpak/install/with_runtime.txt"), "", 0);
+    let exit = mock.run_json(&[
+        "packages",
+        "install",
+        "org.gnome.Calculator",
+        "--source",
+        "flatpak",
+        "--user",
+    ]);
+    assert_eq!(exit["packages_response"]["packages_length"], 2);
+    assert_eq!(
+        mock.calls(),
+        vec!["flatpak install --user -y --noninteractive org.gnome.Calculator"]
+    );
+}
+
+#[test]
+fn snap_source() {
+    let mock = Mock::os("snap_source", "ubuntu");
+    mock.respond(&fixture("snap/list/installed.txt"), "", 0);
+    let exit = mock.run_json(&["packages", "list", "--source", "snap"]);
+    assert_eq!(exit["real_command"], "snap");
+    assert_eq!(
+        exit["packages_response"],
+        snapshot("snap/list/installed.json")
+    );
+    assert_eq!(mock.calls(), vec!["snap list"]);
+}
+
+#[test]
+fn all_sources_list() {
+    let mock = Mock::os("all_sources_list", "ubuntu");
+    mock.install("flatpak").install("snap");
+    mock.respond(&fixture("apt/list/installed.txt"), "", 0)
+        .respond(&fixture("flatpak/list/installed.txt"), "", 0)
+        .respond(&fixture("snap/list/installed.txt"), "", 0);
+    let exit = mock.run_json(&["packages", "list", "--all-sources"]);
+    assert_eq!(exit["exit_code"], 0);
+    assert_eq!(exit["real_command"], "multiple");
+    let response = &exit["packages_response"];
+    let sources: Vec<&str> = response["sources"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|source| source["source"].as_str().unwrap())
+        .collect();
+    assert_eq!(sources, vec!["apt", "flatpak", "snap"]);
+    let lengths: Vec<u64> = ["apt", "flatpak", "snap"]
+        .iter()
+        .map(|manager| {
+            let snapshot = snapshot(&format!("{}/list/installed.json", manager));
+            return snapshot["packages_length"].as_u64().unwrap();
+        })
+        .collect();
+    assert_eq!(response["packages_length"], lengths.iter().sum::<u64>());
+    let packages = response["packages"].as_array().unwrap();
+    assert_eq!(packages[0]["source"], "apt");
+    assert_eq!(packages[packages.len() - 1]["source"], "snap");
+    assert_eq!(mock.calls().len(), 3);
+}
+
+#[test]
+fn all_sources_partial_failure() {
+    let mock = Mock::os("all_sources_partial_failure", "ubuntu");
+    mock.install("flatpak");
+    mock.respond(&fixture("apt/refresh/up_to_date.txt"), "", 0)
+        .respond(&fixture("apt/update/upgrade.txt"), "", 0)
+        .respond("", "error: Unable to connect to system bus", 1);
+    let exit = mock.run_json(&["packages", "update", "--all-sources"]);
+    assert_eq!(exit["exit_code"], 1);
+    assert_eq!(exit["error_kind"], "command_failed");
+    let sources = &exit["packages_response"]["sources"];
+    assert_eq!(sources[0]["source"], "apt");
+    assert_eq!(sources[0]["exit_code"], 0);
+    assert_eq!(sources[1]["source"], "flatpak");
+    assert_eq!(sources[1]["error_kind"], "command_failed");
+    assert_eq!(
+        exit["packages_response"]["packages_length"],
+        snapshot("apt/update/upgrade.json")["packages_length"]
+    );
+}
+
+#[test]
+fn all_sources_unsupported_subcommand() {
+    let mock = Mock::os("all_sources_unsupported_subcommand", "ubuntu");
+    let exit = mock.run_json(&["packages", "install", "curl", "--all-sources"]);
+    assert_eq!(exit["exit_code"], 2);
+    assert_eq!(exit["error_kind"], "unsupported_operation");
+    assert!(mock.calls().is_empty());
+}
+
+#[test]
+fn stream_json_events() {
+    let mock = Mock::os("stream_json_events", "alpine");
+    mock.respond(&fixture("apk/install/with_dependencies.txt"), "", 0);
+    let result = mock.run(&["-o", "json", "--stream", "packages", "install", "curl"]);
+    let records: Vec<Value> = String::from_utf8(result.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    // A progress event for every line of output, then the Exit
+    assert_eq!(records.len(), 9);
+    assert_eq!(
+        records[1]["progress"],
+        json!({
+            "phase": "installing",
+            "package": "brotli-libs",
+            "download_bytes": null,
+            "percent": 40,
+            "line": "(2/5) Installing brotli-libs (1.1.0-r1)",
+        })
+    );
+    assert_eq!(records[5]["progress"]["phase"], "configuring");
+    assert_eq!(records[7]["progress"]["phase"], "output");
+    assert_eq!(records[8]["exit"]["exit_code"], 0);
+    assert_eq!(
+        records[8]["exit"]["packages_response"]["packages_length"],
+        5
+    );
+}
+
+#[test]
+fn stream_msgpack_is_length_prefixed() {
+    let mock = Mock::os("stream_msgpack_is_length_prefixed", "alpine");
+    mock.respond(&fixture("apk/install/with_dependencies.txt"), "", 0);
+    let result = mock.run(&["-o", "msgpack", "--stream", "packages", "install", "curl"]);
+    let mut records: Vec<Value> = vec![];
+    let mut rest = result.stdout.as_slice();
+    while !rest.is_empty() {
+        let length = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+        records.push(rmp_serde::from_slice(&rest[4..4 + length]).unwrap());
+        rest = &rest[4 + length..];
+    }
+    assert_eq!(records.len(), 9);
+    assert_eq!(records[0]["progress"][1], "ca-certificates");
+    assert_eq!(records[8]["exit"][0], 0);
+}
+
+#[test]
+fn dry_run_accepts_aborted_transaction() {
+    let mock = Mock::os("dry_run_accepts_aborted_transaction", "fedora");
+    mock.respond(
+        &fixture("dnf/install/assumeno.txt"),
+        "Operation aborted.\n",
+        1,
+    );
+    let exit = mock.run_json(&["packages", "install", "ripgrep", "--dry-run"]);
+    assert_eq!(exit["exit_code"], 0);
+    let response = &exit["packages_response"];
+    assert_eq!(response["packages_length"], 2);
+    assert_eq!(
+        response["messages"],
+        json!(["Dry run, nothing was changed"])
+    );
+    assert_eq!(
+        mock.calls().last().unwrap(),
+        "dnf install --assumeno ripgrep"
+    );
+}
+
+#[test]
+fn dry_run_keeps_real_failures() {
+    let mock = Mock::os("dry_run_keeps_real_failures", "fedora");
+    mock.respond("", "No match for argument: nonexistent\n", 1);
+    let exit = mock.run_json(&["packages", "install", "nonexistent", "--dry-run"]);
+    assert_eq!(exit["exit_code"], 1);
+    assert_eq!(exit["error_kind"], "command_failed");
+}
+
+#[test]
+fn dry_run_simulates_with_apt_get() {
+    let mock = Mock::os("dry_run_simulates_with_apt_get", "debian");
+    mock.respond(&fixture("apt/uninstall/simulated.txt"), "", 0);
+    let exit = mock.run_json(&["packages", "uninstall", "ripgrep", "--dry-run"]);
+    assert_eq!(exit["packages_response"]["uninstalled_packages_length"], 1);
+    assert_eq!(mock.calls(), vec!["apt-get -s remove ripgrep"]);
+}
+
+#[test]
+fn dry_run_unsupported_by_flatpak() {
+    let mock = Mock::os("dry_run_unsupported_by_flatpak", "fedora");
+    let exit = mock.run_json(&[
+        "packages",
+        "install",
+        "org.gnome.Calculator",
+        "--source",
+        "flatpak",
+        "--dry-run",
+    ]);
+    assert_eq!(exit["error_kind"], "unsupported_operation");
+    assert!(mock.calls().is_empty());
+}
+
+#[test]
+fn history_reads_apt_log() {
+    let mock = Mock::os("history_reads_apt_log", "debian");
+    mock.log("apt/history.log", &fixture("apt/history/history.txt"));
+    let exit = mock.run_json(&["packages", "history", "--limit", "2"]);
+    assert_eq!(exit["exit_code"], 0);
+    let transactions = &exit["packages_response"]["transactions"];
+    assert_eq!(transactions.as_array().unwrap().len(), 2);
+    assert_eq!(transactions[0]["id"], "3");
+    assert_eq!(transactions[1]["command"], "apt install ripgrep");
+    assert!(mock.calls().is_empty());
+}
+
+#[test]
+fn history_runs_dnf_history_info() {
+    let mock = Mock::os("history_runs_dnf_history_info", "fedora");
+    mock.respond(
+        "ID     | Command line  | Date and time    | Action(s)      | Altered\n\
+         -------------------------------------------------------------------\n\
+         \x20    3 | upgrade -y    | 2024-04-03 20:15 | Upgrade        |    2\n\
+         \x20    2 | install ripgrep | 2024-04-01 10:00 | Install      |    2\n",
+        "",
+        0,
+    )
+    .respond(&fixture("dnf/history/dnf4.txt"), "", 0)
+    .respond("", "", 0);
+    let exit = mock.run_json(&["packages", "history", "--limit", "2"]);
+    assert_eq!(exit["exit_code"], 0);
+    assert_eq!(
+        mock.calls(),
+        vec![
+            "dnf history list",
+            "dnf history info 3",
+            "dnf history info 2"
+        ]
+    );
+    let transactions = &exit["packages_response"]["transactions"];
+    assert_eq!(transactions[0]["action"], "upgrade, remove");
+    assert_eq!(
+        transactions[0]["packages"][0]["old_version"],
+        "8.6.0-3.fc39"
+    );
+}
+
+#[test]
+fn history_undo_with_dnf() {
+    let mock = Mock::os("history_undo_with_dnf", "fedora");
+    mock.respond(&fixture("dnf/undo/undo.txt"), "", 0);
+    let exit = mock.run_json(&["packages", "history", "undo", "3"]);
+    assert_eq!(exit["exit_code"], 0);
+    assert_eq!(exit["packages_response"]["packages_length"], 2);
+    assert_eq!(mock.calls(), vec!["dnf history undo -y 3"]);
+}
+
+#[test]
+fn history_undo_unsupported_by_apt() {
+    let mock = Mock::os("history_undo_unsupported_by_apt", "debian");
+    let exit = mock.run_json(&["packages", "history", "undo", "1"]);
+    assert_eq!(exit["exit_code"], 2);
+    assert_eq!(exit["error_kind"], "unsupported_operation");
+    assert!(mock.calls().is_empty());
+}
+
+#[test]
+fn repos_disable_dnf_repo() {
+    let mock = Mock::os("repos_disable_dnf_repo", "fedora");
+    mock.etc("yum.repos.d/fedora.repo", &fixture("dnf/repos/fedora.txt"));
+    let exit = mock.run_json(&["packages", "repos"]);
+    let repositories = &exit["packages_response"]["repositories"];
+    assert_eq!(repositories.as_array().unwrap().len(), 2);
+    assert_eq!(repositories[0]["enabled"], true);
+
+    let exit = mock.run_json(&["packages", "repos", "disable", "fedora"]);
+    assert_eq!(exit["exit_code"], 0);
+    assert_eq!(
+        exit["packages_response"]["repositories"][0]["enabled"],
+        false
+    );
+    let repo = mock.read_etc("yum.repos.d/fedora.repo").unwrap();
+    assert!(repo.contains("[fedora]\nname=Fedora $releasever - $basearch\n"));
+    assert_eq!(repo.matches("enabled=0").count(), 2);
+    assert!(mock.calls().is_empty());
+}
+
+#[test]
+fn repos_add_and_remove_apt_repo() {
+    let mock = Mock::os("repos_add_and_remove_apt_repo", "debian");
+    mock.etc("apt/sources.list", &fixture("apt/repos/sources_list.txt"));
+    let exit = mock.run_json(&[
+        "packages",
+        "repos",
+        "add",
+        "https://download.docker.com/linux/debian",
+        "--id",
+        "docker",
+        "--suites",
+        "bookworm",
+        "--components",
+        "stable",
+    ]);
+    assert_eq!(exit["exit_code"], 0);
+    assert_eq!(
+        mock.read_etc("apt/sources.list.d/docker.sources").unwrap(),
+        "Types: deb\nURIs: https://download.docker.com/linux/debian\nSuites: bookworm\nComponents: stable\n"
+    );
+    let exit = mock.run_json(&["packages", "repos", "list"]);
+    let repositories = exit["packages_response"]["repositories"]
+        .as_array()
+        .unwrap()
+        .clone();
+    let ids: Vec<&str> = repositories
+        .iter()
+        .map(|repo| repo["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        ids,
+        vec![
+            "sources:1",
+            "sources:2",
+            "sources:3",
+            "sources:4",
+            "sources:5",
+            "docker"
+        ]
+    );
+
+    let exit = mock.run_json(&["packages", "repos", "remove", "docker"]);
+    assert_eq!(exit["exit_code"], 0);
+    assert_eq!(mock.read_etc("apt/sources.list.d/docker.sources"), None);
+}
+
+#[test]
+fn repos_enable_commented_apt_entry() {
+    let mock = Mock::os("repos_enable_commented_apt_entry", "debian");
+    mock.etc("apt/sources.list", &fixture("apt/repos/sources_list.txt"));
+    let exit = mock.run_json(&["packages", "repos", "enable", "sources:5"]);
+    assert_eq!(
+        exit["packages_response"]["repositories"][0]["enabled"],
+        true
+    );
+    let sources = mock.read_etc("apt/sources.list").unwrap();
+    assert!(sources.ends_with("\ndeb [arch=amd64 trusted=yes] http://repo.example.com/debian ./\n"));
+}
+
+#[test]
+fn repos_add_tagged_apk_repo() {
+    let mock = Mock::os("repos_add_tagged_apk_repo", "alpine");
+    mock.etc(
+        "apk/repositories",
+        "https://dl-cdn.alpinelinux.org/alpine/v3.19/main",
+    );
+    let exit = mock.run_json(&[
+        "packages",
+        "repos",
+        "add",
+        "https://dl-cdn.alpinelinux.org/alpine/edge/testing",
+        "--id",
+        "testing",
+    ]);
+    assert_eq!(exit["exit_code"], 0);
+    assert_eq!(
+        mock.read_etc("apk/repositories").unwrap(),
+        "https://dl-cdn.alpinelinux.org/alpine/v3.19/main\n@testing https://dl-cdn.alpinelinux.org/alpine/edge/testing\n"
+    );
+}
+
+#[test]
+fn repos_unknown_id() {
+    let mock = Mock::os("repos_unknown_id", "opensuse-tumbleweed");
+    mock.etc(
+        "zypp/repos.d/repo-oss.repo",
+        &fixture("zypper/repos/repo-oss.txt"),
+    );
+    let exit = mock.run_json(&["packages", "repos", "remove", "nonexistent"]);
+    assert_eq!(exit["exit_code"], 1);
+    assert_eq!(exit["error_kind"], "not_found");
+    assert!(mock.read_etc("zypp/repos.d/repo-oss.repo").is_some());
+}
+
+#[test]
+fn hold_with_apt_mark() {
+    let mock = Mock::os("hold_with_apt_mark", "debian");
+    mock.respond("curl set on hold.\n", "", 0)
+        .respond(&fixture("apt/holds/held.txt"), "", 0);
+    let exit = mock.run_json(&["packages", "hold", "curl"]);
+    assert_eq!(exit["exit_code"], 0);
+    let response = &exit["packages_response"];
+    assert_eq!(response["subcommand"], "hold");
+    assert_eq!(response["messages"], json!(["Held curl"]));
+    assert_eq!(response["packages"][0]["version"], "7.88.1-10+deb12u8");
+    assert_eq!(mock.calls()[0], "apt-mark hold curl");
+}
+
+#[test]
+fn hold_pins_apk_version() {
+    let mock = Mock::os("hold_pins_apk_version", "alpine");
+    mock.etc("apk/world", "alpine-base\ncurl=8.5.0-r0\n");
+    mock.respond("curl-8.5.0-r0 x86_64 {curl} (curl) [installed]\n", "", 0)
+        .respond("OK: 12 MiB in 18 packages\n", "", 0);
+    let exit = mock.run_json(&["packages", "hold", "curl"]);
+    assert_eq!(exit["exit_code"], 0);
+    assert_eq!(exit["packages_response"]["packages_length"], 1);
+    assert_eq!(
+        mock.calls(),
+        vec!["apk list -I curl", "apk add curl=8.5.0-r0"]
+    );
+}
+
+#[test]
+fn holds_reads_zypper_locks() {
+    let mock = Mock::os("holds_reads_zypper_locks", "opensuse-tumbleweed");
+    mock.etc("zypp/locks", &fixture("zypper/holds/locks.txt"));
+    let exit = mock.run_json(&["packages", "holds"]);
+    assert_eq!(exit["packages_response"]["packages_length"], 2);
+    assert!(mock.calls().is_empty());
+}
+
+#[test]
+fn holds_unsupported_by_snap() {
+    let mock = Mock::os("holds_unsupported_by_snap", "ubuntu");
+    mock.install("snap");
+    let exit = mock.run_json(&["packages", "--source", "snap", "holds"]);
+    assert_eq!(exit["exit_code"], 2);
+    assert_eq!(exit["error_message"], "snap can't list held packages");
+}
+
+#[test]
+fn update_security_with_dnf() {
+    let mock = Mock::os("update_security_with_dnf", "fedora");
+    let advisories = fixture("dnf/advisories/dnf4.txt");
+    let (list, with_cve) = advisories.split_at(advisories.rfind("Last metadata").unwrap());
+    mock.respond(list, "", 0).respond(with_cve, "", 0).respond(
+        &fixture("dnf/update/dnf4.txt"),
+        "",
+        0,
+    );
+    let exit = mock.run_json(&["packages", "update", "--security"]);
+    assert_eq!(exit["exit_code"], 0);
+    let packages = &exit["packages_response"]["packages"];
+    assert_eq!(packages[0]["name"], "curl");
+    assert_eq!(packages[0]["advisories"][0]["id"], "FEDORA-2024-1a2b3c4d5e");
+    assert_eq!(
+        packages[0]["advisories"][0]["cves"],
+        json!(["CVE-2024-2004", "CVE-2024-2398"])
+    );
+    assert_eq!(packages[2]["advisories"], json!([]));
+    assert_eq!(mock.calls()[2], "dnf upgrade --security -y");
+}
+
+#[test]
+fn fake_update_unsupported_by_apk() {
+    let mock = Mock::os("fake_update_unsupported_by_apk", "alpine");
+    let exit = mock.run_json(&["packages", "update", "--fake"]);
+    assert_eq!(exit["exit_code"], 2);
+    assert_eq!(exit["error_kind"], "unsupported_operation");
+    assert_eq!(mock.calls(), Vec::<String>::new());
+}
+
+#[test]
+fn fake_update_unsupported_by_snap() {
+    let mock = Mock::os("fake_update_unsupported_by_snap", "ubuntu");
+    mock.install("snap");
+    let exit = mock.run_json(&["packages", "--source", "snap", "update", "--fake"]);
+    assert_eq!(exit["exit_code"], 2);
+    assert_eq!(exit["error_kind"], "unsupported_operation");
+    assert_eq!(mock.calls(), Vec::<String>::new());
+}
+
+#[test]
+fn advisories_from_debian_security_suite() {
+    let mock = Mock::os("advisories_from_debian_security_suite", "debian");
+    mock.respond(&fixture("apt/advisories/upgradable.txt"), "", 0);
+    let exit = mock.run_json(&["packages", "advisories", "--security"]);
+    let advisories = &exit["packages_response"]["advisories"];
+    assert_eq!(advisories.as_array().unwrap().len(), 2);
+    assert_eq!(advisories[0]["id"], "curl/stable-security");
+    assert_eq!(mock.calls(), vec!["apt list --upgradable"]);
+}
+
+#[test]
+fn update_unknown_zypper_advisory() {
+    let mock = Mock::os("update_unknown_zypper_advisory", "opensuse-tumbleweed");
+    mock.respond(&fixture("zypper/advisories/patches.txt"), "", 0)
+        .respond(&fixture("zypper/patch_info/curl.txt"), "", 0);
+    let exit = mock.run_json(&["packages", "update", "--advisory", "SUSE-2024-0000"]);
+    assert_eq!(exit["exit_code"], 1);
+    assert_eq!(exit["error_kind"], "not_found");
+    assert_eq!(mock.calls().len(), 2);
+}
+
+#[test]
+fn autoremove_with_apt() {
+    let mock = Mock::os("autoremove_with_apt", "debian");
+    mock.respond(&fixture("apt/autoremove/autoremove.txt"), "", 0);
+    let exit = mock.run_json(&["packages", "autoremove"]);
+    assert_eq!(exit["exit_code"], 0);
+    let response = &exit["packages_response"];
+    assert_eq!(response["subcommand"], "autoremove");
+    assert_eq!(response["uninstalled_packages_length"], 2);
+    assert_eq!(response["uninstalled_packages_size"], "390.05 MiB");
+    assert_eq!(mock.calls(), vec!["apt autoremove -y"]);
+}
+
+#[test]
+fn autoremove_unneeded_zypper_packages() {
+    let mock = Mock::os("autoremove_unneeded_zypper_packages", "opensuse-tumbleweed");
+    mock.respond(&fixture("zypper/unneeded/unneeded.txt"), "", 0)
+        .respond(&fixture("zypper/uninstall/with_dependencies.txt"), "", 0);
+    let exit = mock.run_json(&["packages", "autoremove", "--dry-run"]);
+    assert_eq!(exit["exit_code"], 0);
+    assert_eq!(exit["packages_response"]["subcommand"], "autoremove");
+    assert_eq!(
+        mock.calls(),
+        vec![
+            "zypper --non-interactive packages --unneeded",
+            "zypper --non-interactive --xmlout remove --dry-run libopenssl1_1 python311-six"
+        ]
+    );
+}
+
+#[test]
+fn autoremove_unsupported_by_apk() {
+    let mock = Mock::os("autoremove_unsupported_by_apk", "alpine");
+    let exit = mock.run_json(&["packages", "autoremove"]);
+    assert_eq!(exit["exit_code"], 2);
+    assert_eq!(exit["error_kind"], "unsupported_operation");
+}
+
+#[test]
+fn clean_reports_freed_bytes() {
+    let mock = Mock::os("clean_reports_freed_bytes", "debian");
+    mock.cache(
+        "apt/archives/curl_7.88.1-10+deb12u8_amd64.deb",
+        &"x".repeat(2048),
+    )
+    .cache("apt/pkgcache.bin", &"x".repeat(1024))
+    .respond("", "", 0)
+    .effect("rm \"$SYSADMIN_CACHE_DIR\"/apt/archives/*.deb");
+    let exit = mock.run_json(&["packages", "clean"]);
+    assert_eq!(exit["exit_code"], 0);
+    let response = &exit["packages_response"];
+    assert_eq!(response["uninstalled_packages_size_bytes"], 2048);
+    assert_eq!(response["messages"], json!(["Freed 2.00 KiB"]));
+    assert_eq!(mock.calls(), vec!["apt clean"]);
+
+    mock.respond("", "", 0);
+    let exit = mock.run_json(&["packages", "clean"]);
+    assert_eq!(
+        exit["packages_response"]["messages"],
+        json!(["The cache is clean already"])
+    );
+}
+
+#[test]
+fn depends_with_dnf5_marks_user_installed() {
+    let mock = Mock::os("depends_with_dnf5_marks_user_installed", "fedora");
+    let installed = fixture("dnf/list/installed.txt");
+    let bash = installed.split_inclusive('\x1e').next().unwrap();
+    mock.respond("dnf5 version 5.1.15\n", "", 0)
+        .respond(&installed, "", 0)
+        .respond(bash, "", 0);
+    let exit = mock.run_json(&["packages", "depends", "dnf5"]);
+    assert_eq!(exit["exit_code"], 0);
+    let packages = &exit["packages_response"]["packages"];
+    assert_eq!(packages[0]["name"], "bash");
+    assert_eq!(packages[0]["user_installed"], true);
+    assert_eq!(packages[1]["user_installed"], false);
+    let calls = mock.calls();
+    assert!(calls[1].ends_with("--installed --providers-of=requires dnf5"));
+    assert!(calls[2].ends_with("--userinstalled"));
+}
+
+#[test]
+fn why_follows_apt_rdepends_to_manual_packages() {
+    let mock = Mock::os("why_follows_apt_rdepends_to_manual_packages", "debian");
+    mock.respond("curl\ngit\n", "", 0)
+        .respond(
+            "libcurl3-gnutls\nReverse Depends:\n  Depends: curl\n  Depends: git-man\n  Suggests: libcurl-doc\n",
+            "",
+            0,
+        )
+        .respond("git-man\nReverse Depends:\n  Depends: git\n", "", 0);
+    let exit = mock.run_json(&["packages", "why", "libcurl3-gnutls"]);
+    assert_eq!(exit["exit_code"], 0);
+    let root = &exit["packages_response"]["packages"][0];
+    assert_eq!(root["name"], "libcurl3-gnutls");
+    assert_eq!(root["user_installed"], false);
+    assert_eq!(root["required_by"][0]["name"], "curl");
+    assert_eq!(root["required_by"][0]["required_by"], json!([]));
+    assert_eq!(root["required_by"][1]["name"], "git-man");
+    assert_eq!(root["required_by"][1]["required_by"][0]["name"], "git");
+    assert_eq!(root["required_by"].as_array().unwrap().len(), 2);
+    assert_eq!(
+        mock.calls(),
+        vec![
+            "apt-mark showmanual",
+            "apt-cache rdepends --installed --no-suggests --no-recommends --no-conflicts --no-breaks --no-replaces --no-enhances -o APT::Cache::ShowDependencyType=true libcurl3-gnutls",
+            "apt-cache rdepends --installed --no-suggests --no-recommends --no-conflicts --no-breaks --no-replaces --no-enhances -o APT::Cache::ShowDependencyType=true git-man"
+        ]
+    );
+}
+
+#[test]
+fn why_explicit_apk_package() {
+    let mock = Mock::os("why_explicit_apk_package", "alpine");
+    mock.etc("apk/world", "alpine-base\ncurl=8.5.0-r0\n");
+    let exit = mock.run_json(&["packages", "why", "curl"]);
+    assert_eq!(
+        exit["packages_response"]["messages"],
+        json!(["curl was installed explicitly"])
+    );
+    assert!(mock.calls().is_empty());
+}
+
+#[test]
+fn owner_from_rpm_with_dnf() {
+    let mock = Mock::os("owner_from_rpm_with_dnf", "fedora");
+    mock.respond(&fixture("rpm/owner/curl.txt"), "", 0);
+    let exit = mock.run_json(&["packages", "owner", "/usr/bin/curl"]);
+    assert_eq!(exit["exit_code"], 0);
+    let owner = &exit["packages_response"]["packages"][0];
+    assert_eq!(owner["name"], "curl");
+    assert_eq!(owner["version"], "8.6.0-4.fc40");
+    assert_eq!(owner["files"], json!(["/usr/bin/curl"]));
+    assert_eq!(
+        mock.calls(),
+        vec![
+            r"rpm -qf --queryformat %{NAME}\t%{ARCH}\t%{EPOCHNUM}\t%{VERSION}\t%{RELEASE}\n /usr/bin/curl"
+        ]
+    );
+}
+
+#[test]
+fn files_from_dpkg_with_version() {
+    let mock = Mock::os("files_from_dpkg_with_version", "debian");
+    mock.respond(&fixture("apt/files/curl.txt"), "", 0).respond(
+        "curl\tamd64\t7.88.1-10+deb12u8\n",
+        "",
+        0,
+    );
+    let exit = mock.run_json(&["packages", "files", "curl"]);
+    assert_eq!(exit["exit_code"], 0);
+    let package = &exit["packages_response"]["packages"][0];
+    assert_eq!(package["name"], "curl");
+    assert_eq!(package["arch"], "amd64");
+    assert_eq!(package["version"], "7.88.1-10+deb12u8");
+    assert_eq!(package["files"][2], "/usr/bin/curl");
+    assert_eq!(mock.calls()[0], "dpkg -L curl");
+}
+
+#[test]
+fn owner_not_found_with_apk() {
+    let mock = Mock::os("owner_not_found_with_apk", "alpine");
+    mock.respond(
+        "",
+        "ERROR: /usr/bin/nothing: Could not find owner package\n",
+        1,
+    );
+    let exit = mock.run_json(&["packages", "owner", "/usr/bin/nothing"]);
+    assert_eq!(exit["exit_code"], 1);
+    assert_eq!(exit["error_kind"], "not_found");
+    assert_eq!(exit["error_message"], "No package owns /usr/bin/nothing");
+}
+
+#[test]
+fn files_not_installed_with_rpm() {
+    let mock = Mock::os("files_not_installed_with_rpm", "fedora");
+    mock.respond("package nothing is not installed\n", "", 1);
+    let exit = mock.run_json(&["packages", "files", "nothing"]);
+    assert_eq!(exit["exit_code"], 1);
+    assert_eq!(exit["error_kind"], "not_found");
+}
+
+#[test]
+fn owner_passes_dpkg_failures_through() {
+    let mock = Mock::os("owner_passes_dpkg_failures_through", "debian");
+    mock.respond(
+        "",
+        "dpkg-query: error: parsing file '/var/lib/dpkg/status' near line 12:\n missing 'Package' field\n",
+        1,
+    );
+    let exit = mock.run_json(&["packages", "owner", "/usr/bin/curl"]);
+    assert_eq!(exit["exit_code"], 1);
+    assert_eq!(exit["error_kind"], "command_failed");
+    assert!(exit["error_message"]
+        .as_str()
+        .unwrap()
+        .contains("missing 'Package' field"));
+}
+
+#[test]
+fn files_unsupported_by_snap_source() {
+    let mock = Mock::os("files_unsupported_by_snap_source", "ubuntu");
+    mock.install("snap");
+    let exit = mock.run_json(&["packages", "--source", "snap", "files", "firefox"]);
+    assert_eq!(exit["exit_code"], 2);
+    assert_eq!(exit["error_kind"], "unsupported_operation");
+}
+
+#[test]
+fn verify_with_rpm_reports_findings() {
+    let mock = Mock::os("verify_with_rpm_reports_findings", "fedora");
+    mock.respond(
+        "..5....T.    /usr/bin/curl\nmissing     /usr/share/man/man1/curl.1.gz\n",
+        "",
+        1,
+    )
+    .respond("", "", 0);
+    let exit = mock.run_json(&["packages", "verify", "curl", "bash"]);
+    assert_eq!(exit["exit_code"], 0);
+    let response = &exit["packages_response"];
+    assert_eq!(
+        response["messages"],
+        json!(["2 files differ from their packages"])
+    );
+    assert_eq!(response["findings"][0]["package"], "curl");
+    assert_eq!(
+        response["findings"][0]["problems"],
+        json!(["checksum", "mtime"])
+    );
+    assert_eq!(response["findings"][1]["problems"], json!(["missing"]));
+    assert_eq!(mock.calls(), vec!["rpm -V curl", "rpm -V bash"]);
+}
+
+#[test]
+fn verify_not_installed_with_zypper() {
+    let mock = Mock::os("verify_not_installed_with_zypper", "opensuse-tumbleweed");
+    mock.respond("package nothing is not installed\n", "", 1);
+    let exit = mock.run_json(&["packages", "verify", "nothing"]);
+    assert_eq!(exit["exit_code"], 1);
+    assert_eq!(exit["error_kind"], "not_found");
+}
+
+#[test]
+fn verify_whole_system_with_dpkg() {
+    let mock = Mock::os("verify_whole_system_with_dpkg", "debian");
+    mock.respond("", "", 0);
+    let exit = mock.run_json(&["packages", "verify"]);
+    assert_eq!(
+        exit["packages_response"]["messages"],
+        json!(["All files match their packages"])
+    );
+    assert_eq!(mock.calls(), vec!["dpkg --verify"]);
+}
+
+#[test]
+fn verify_single_packages_unsupported_by_apk() {
+    let mock = Mock::os("verify_single_packages_unsupported_by_apk", "alpine");
+    let exit = mock.run_json(&["packages", "verify", "curl"]);
+    assert_eq!(exit["exit_code"], 2);
+    assert!(mock.calls().is_empty());
+}
@@ -9,6 +9,12 @@ export class Exit {
 		if (msgpack[4] != null) {
 			this.error_message = msgpack[4];
 		}
+		if (msgpack[5] != null) {
+			this.error_kind = msgpack[5];
+		}
+		if (msgpack[6] != null) {
+			this.os_info = new OsInfo(msgpack[6]);
+		}
 	}
 
 	exit_code: number;
@@ -16,8 +22,22 @@ export class Exit {
 	out: string;
 	packages_response?: PackagesResponse;
 	error_message?: string;
+	error_kind?: ErrorKind;
+	os_info?: OsInfo;
 }
 
+export type ErrorKind =
+	| 'backend_missing'
+	| 'unsupported_os'
+	| 'unknown_os'
+	| 'command_failed'
+	| 'parse_failure'
+	| 'permission_denied'
+	| 'unreadable_output'
+	| 'io'
+	| 'unsupported_operation'
+	| 'not_found';
+
 class PackagesResponse {
 	constructor(msgpack: Array<any>) {
 		this.subcommand = msgpack[0];
@@ -34,6 +54,28 @@ class PackagesResponse {
 		}
 		this.uninstalled_packages_size = msgpack[6];
 		this.uninstalled_packages_length = msgpack[7];
+		this.packages_size_bytes = msgpack[8];
+		this.uninstalled_packages_size_bytes = msgpack[9];
+		this.sources = [];
+		for (let i = 0; i < msgpack[10].length; i++) {
+			this.sources.push(new SourceResponse(msgpack[10][i]));
+		}
+		this.transactions = [];
+		for (let i = 0; i < msgpack[11].length; i++) {
+			this.transactions.push(new Transaction(msgpack[11][i]));
+		}
+		this.repositories = [];
+		for (let i = 0; i < msgpack[12].length; i++) {
+			this.repositories.push(new Repository(msgpack[12][i]));
+		}
+		this.advisories = [];
+		for (let i = 0; i < msgpack[13].length; i++) {
+			this.advisories.push(new Advisory(msgpack[13][i]));
+		}
+		this.findings = [];
+		for (let i = 0; i < msgpack[14].length; i++) {
+			this.findings.push(new Finding(msgpack[14][i]));
+		}
 	}
 	subcommand: string;
 	messages: Array<string>;
@@ -43,6 +85,115 @@ class PackagesResponse {
 	uninstalled_packages: Array<Package>;
 	uninstalled_packages_size?: string;
 	uninstalled_packages_length?: number;
+	packages_size_bytes?: number;
+	uninstalled_packages_size_bytes?: number;
+	sources: Array<SourceResponse>;
+	transactions: Array<Transaction>;
+	repositories: Array<Repository>;
+	advisories: Array<Advisory>;
+	findings: Array<Finding>;
+}
+
+class Finding {
+	constructor(msgpack: Array<any>) {
+		this.path = msgpack[0];
+		this.package = msgpack[1];
+		this.config = msgpack[2];
+		this.problems = msgpack[3];
+	}
+
+	path: string;
+	package?: string;
+	config: boolean;
+	problems: Array<string>;
+}
+
+class Repository {
+	constructor(msgpack: Array<any>) {
+		this.id = msgpack[0];
+		this.name = msgpack[1];
+		this.urls = msgpack[2];
+		this.enabled = msgpack[3];
+		this.gpgcheck = msgpack[4];
+		this.priority = msgpack[5];
+		this.suites = msgpack[6];
+		this.components = msgpack[7];
+		this.file = msgpack[8];
+	}
+
+	id: string;
+	name?: string;
+	urls: Array<string>;
+	enabled: boolean;
+	gpgcheck?: boolean;
+	priority?: number;
+	suites: Array<string>;
+	components: Array<string>;
+	file?: string;
+}
+
+class Transaction {
+	constructor(msgpack: Array<any>) {
+		this.id = msgpack[0];
+		this.timestamp = msgpack[1];
+		this.user = msgpack[2];
+		this.action = msgpack[3];
+		this.command = msgpack[4];
+		this.packages = [];
+		for (let i = 0; i < msgpack[5].length; i++) {
+			this.packages.push(new Package(msgpack[5][i]));
+		}
+	}
+
+	id: string;
+	timestamp?: string;
+	user?: string;
+	action: string;
+	command?: string;
+	packages: Array<Package>;
+}
+
+class Advisory {
+	constructor(msgpack: Array<any>) {
+		this.id = msgpack[0];
+		this.kind = msgpack[1];
+		this.severity = msgpack[2];
+		this.title = msgpack[3];
+		this.issued = msgpack[4];
+		this.cves = msgpack[5];
+		this.packages = [];
+		for (let i = 0; i < msgpack[6].length; i++) {
+			this.packages.push(new Package(msgpack[6][i]));
+		}
+	}
+
+	id: string;
+	kind?: string;
+	severity?: string;
+	title?: string;
+	issued?: string;
+	cves: Array<string>;
+	packages: Array<Package>;
+}
+
+class SourceResponse {
+	constructor(msgpack: Array<any>) {
+		this.source = msgpack[0];
+		this.exit_code = msgpack[1];
+		this.messages = msgpack[2];
+		this.packages_length = msgpack[3];
+		this.uninstalled_packages_length = msgpack[4];
+		this.error_message = msgpack[5];
+		this.error_kind = msgpack[6];
+	}
+
+	source: string;
+	exit_code: number;
+	messages: Array<string>;
+	packages_length?: number;
+	uninstalled_packages_length?: number;
+	error_message?: string;
+	error_kind?: ErrorKind;
 }
 
 class Package {
@@ -58,6 +209,22 @@ class Package {
 		this.url = msgpack[8];
 		this.license = msgpack[9];
 		this.installed = msgpack[10];
+		this.size_bytes = msgpack[11];
+		this.download_bytes = msgpack[12];
+		this.installation = msgpack[13];
+		this.channel = msgpack[14];
+		this.confinement = msgpack[15];
+		this.source = msgpack[16];
+		this.advisories = [];
+		for (let i = 0; i < msgpack[17].length; i++) {
+			this.advisories.push(new Advisory(msgpack[17][i]));
+		}
+		this.user_installed = msgpack[18];
+		this.required_by = [];
+		for (let i = 0; i < msgpack[19].length; i++) {
+			this.required_by.push(new Package(msgpack[19][i]));
+		}
+		this.files = msgpack[20];
 	}
 
 	name: string;
@@ -71,4 +238,80 @@ class Package {
 	url?: string;
 	license?: string;
 	installed?: boolean;
+	size_bytes?: number;
+	download_bytes?: number;
+	installation?: 'system' | 'user';
+	channel?: string;
+	confinement?: 'strict' | 'classic' | 'devmode';
+	source?: string;
+	advisories: Array<Advisory>;
+	user_installed?: boolean;
+	required_by: Array<Package>;
+	files: Array<string>;
+}
+
+export type Phase =
+	| 'refreshing'
+	| 'downloading'
+	| 'installing'
+	| 'updating'
+	| 'removing'
+	| 'configuring'
+	| 'verifying'
+	| 'output';
+
+export class ProgressEvent {
+	constructor(msgpack: Array<any>) {
+		this.phase = msgpack[0];
+		this.package = msgpack[1];
+		this.download_bytes = msgpack[2];
+		this.percent = msgpack[3];
+		this.line = msgpack[4];
+	}
+
+	phase: Phase;
+	package?: string;
+	download_bytes?: number;
+	percent?: number;
+	line: string;
+}
+
+/** Decodes a record of the "--stream" output, a map with either a "progress" or an "exit" key */
+export function streamRecord(msgpack: { progress?: Array<any>; exit?: Array<any> }): ProgressEvent | Exit {
+	if (msgpack.progress != null) {
+		return new ProgressEvent(msgpack.progress);
+	}
+	return new Exit(msgpack.exit!);
+}
+
+class OsInfo {
+	constructor(msgpack: Array<any>) {
+		this.os_id = msgpack[0];
+		this.os_name = msgpack[1];
+		this.os_version = msgpack[2];
+		this.kernel = msgpack[3];
+		this.hostname = msgpack[4];
+		this.uptime_seconds = msgpack[5];
+		this.boot_time = msgpack[6];
+		this.cpu_model = msgpack[7];
+		this.cpu_count = msgpack[8];
+		this.memory_total_bytes = msgpack[9];
+		this.memory_available_bytes = msgpack[10];
+		this.swap_total_bytes = msgpack[11];
+		this.swap_free_bytes = msgpack[12];
+	}
+
+	os_id?: string;
+	os_name?: string;
+	os_version?: string;
+	kernel?: string;
+	hostname?: string;
+	uptime_seconds?: number;
+	boot_time?: number;
+	cpu_model?: string;
+	cpu_count?: number;
+	memory_total_bytes?: number;
+	memory_available_bytes?: number;
+	swap_total_bytes?: number;
+	swap_free_bytes?: number;
 }